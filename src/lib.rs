@@ -1,3 +1,3 @@
-extern crate mopa;
 
-pub mod pla;
\ No newline at end of file
+pub mod pla;
+pub mod render;
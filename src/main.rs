@@ -1,207 +1,244 @@
-use chrono::{Datelike, Duration};
-use chrono::naive::NaiveDate;
 use std::fs::File;
+use std::io;
 use std::io::prelude::*;
 use std::path::Path;
-use clap::Parser;
+use std::process;
+use clap::{ArgEnum, Parser};
 
+use pla2html::pla::diagnostic::{PlaDiagnostic, PlaSeverity};
+use pla2html::pla::error::PlaError;
+#[cfg(feature = "serde")]
+use pla2html::pla::document::PlaDocument;
 use pla2html::pla::parser::PlaParser;
+use pla2html::pla::schedule::PlaSchedule;
+use pla2html::pla::time_zone::PlaTimeZone;
+use pla2html::render::html::HtmlRenderer;
+use pla2html::render::rows::{dependency_links, resource_rows, task_rows, GanttLink, GanttRow};
+use pla2html::render::svg::SvgRenderer;
 
-#[macro_use]
-extern crate horrorshow;
-// use horrorshow::prelude::*;
-use horrorshow::helper::doctype;
+/// File name that stands for standard input or output.
+const STDIO: &str = "-";
+
+/// Which rows to draw in the chart.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum View {
+    /// One row per entry, with children indented beneath their parents
+    Tasks,
+
+    /// One row per resource, with a bar for every entry that uses it
+    Resources,
+}
+
+/// What kind of file to write the chart to.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum Format {
+    /// An HTML page with a table cell per day
+    Html,
+
+    /// A self-contained SVG image
+    Svg,
+
+    /// The resolved schedule as JSON, as described in docs/schedule.md
+    #[cfg(feature = "serde")]
+    Json,
+
+    /// The resolved schedule as YAML, as described in docs/schedule.md
+    #[cfg(feature = "serde")]
+    Yaml,
+}
+
+impl Format {
+    /// Format implied by the extension of `path`, defaulting to HTML.
+    fn from_path(path: &Path) -> Format {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            Some("svg") => Format::Svg,
+            #[cfg(feature = "serde")]
+            Some("json") => Format::Json,
+            #[cfg(feature = "serde")]
+            Some("yaml" | "yml") => Format::Yaml,
+            _ => Format::Html,
+        }
+    }
+}
+
+/// What kind of file to read entries from.
+#[derive(ArgEnum, Clone, Copy, Debug, PartialEq, Eq)]
+enum InputFormat {
+    /// Pla text
+    Pla,
+
+    /// A schedule document as JSON, as described in docs/schedule.md
+    #[cfg(feature = "serde")]
+    Json,
+
+    /// A schedule document as YAML, as described in docs/schedule.md
+    #[cfg(feature = "serde")]
+    Yaml,
+}
+
+impl InputFormat {
+    /// Format implied by the extension of `path`, defaulting to pla.
+    fn from_path(path: &Path) -> InputFormat {
+        match path.extension().and_then(|e| e.to_str()).map(|e| e.to_ascii_lowercase()).as_deref() {
+            #[cfg(feature = "serde")]
+            Some("json") => InputFormat::Json,
+            #[cfg(feature = "serde")]
+            Some("yaml" | "yml") => InputFormat::Yaml,
+            _ => InputFormat::Pla,
+        }
+    }
+}
 
 #[derive(Parser, Debug)]
 #[clap(version, about, long_about = None)]
 struct Args {
-    /// Input file name. Should be in .pla format
+    /// Input file name. Should be in .pla format or, when built with the serde feature, a schedule
+    /// document in .json or .yaml format. Use - to read from standard input
     #[clap(short)]
     input_file:String,
 
-    /// Output file name
-    #[clap(short)]
-    output_file: String,
+    /// Format of the input file. Defaults to the one matching the input file's extension, and pla
+    /// otherwise
+    #[clap(long, arg_enum)]
+    input_format: Option<InputFormat>,
+
+    /// Output file name. Use - to write to standard output
+    #[clap(short, required_unless_present = "check")]
+    output_file: Option<String>,
+
+    /// Check the input for dependency violations, dangling ids and double-booked resources
+    /// instead of rendering it.
+    /// Exits with a non-zero status if any are found.
+    #[clap(long)]
+    check: bool,
+
+    /// Skip lines that can't be parsed, reporting them, and render the rest of the input
+    #[clap(long)]
+    lenient: bool,
+
+    /// Treat unknown commands as errors rather than warnings
+    #[clap(long)]
+    strict: bool,
+
+    /// Which rows to draw in the chart
+    #[clap(long, arg_enum, default_value = "tasks")]
+    view: View,
+
+    /// Format of the output file. Defaults to the one matching the output file's extension, and
+    /// html otherwise
+    #[clap(long, arg_enum)]
+    format: Option<Format>,
+
+    /// Show the comments written about each entry when hovering over its bar in HTML output
+    #[clap(long)]
+    tooltips: bool,
+
+    /// Time zone to draw the chart in, e.g. Europe/London or +01:00. Start times written in
+    /// another zone are converted to it, and those written without one are drawn as written
+    #[clap(long, default_value = "UTC", allow_hyphen_values = true, parse(try_from_str = parse_time_zone))]
+    timezone: PlaTimeZone,
+}
+
+fn parse_time_zone(s: &str) -> Result<PlaTimeZone, String> {
+    s.parse::<PlaTimeZone>().map_err(|e| e.message)
 }
 
 fn main() {
     let args = Args::parse();
 
-    // Parse the input pla file
-    let _pla_parser = match PlaParser::new(Path::new(&args.input_file)) {
+    // Parse the input pla file. Checking reports every problem in the file, so it always keeps
+    // going past lines that can't be parsed.
+    let input_path = Path::new(&args.input_file);
+    let from_stdin = args.input_file == STDIO;
+    let input_format = args.input_format.unwrap_or_else(|| InputFormat::from_path(input_path));
+    let parsed = match input_format {
+        #[cfg(feature = "serde")]
+        InputFormat::Json | InputFormat::Yaml => read_document(&args, input_format),
+        InputFormat::Pla if args.check || args.lenient => {
+            if from_stdin { PlaParser::from_reader_lenient(io::stdin()) } else { PlaParser::new_lenient(input_path) }
+        },
+        InputFormat::Pla => {
+            let pla_parser = if from_stdin { PlaParser::from_reader(io::stdin()) } else { PlaParser::new(input_path) };
+            pla_parser
+                .and_then(|p| if args.strict { p.strict() } else { Ok(p) })
+                .map(|p| {
+                    let warnings = p.get_warnings();
+                    (p, warnings)
+                })
+        },
+    };
+
+    let (pla_parser, mut parse_diagnostics) = match parsed {
         Ok(p) => p,
-        Err(why) => panic!("Unable to parse {} due to {}", args.input_file, why),
+        Err(PlaError::Io(why)) => {
+            eprintln!("{}: {}", args.input_file, why);
+            process::exit(1);
+        },
+        Err(why) => {
+            eprintln!("{}", PlaDiagnostic::from(&why));
+            process::exit(1);
+        },
     };
 
-    // Create the main html page with the grid
-    let start_date = NaiveDate::parse_from_str("2021-10-01", "%Y-%m-%d").unwrap();
-    let end_date = NaiveDate::parse_from_str("2021-12-31", "%Y-%m-%d").unwrap();
-    let duration = end_date - start_date;
-    let duration_days = duration.num_days();
-    let formatted_month_name = format!("{}", start_date.format("%B %Y"));
-    let mut months: Vec<String> = vec![formatted_month_name];
-    let mut month_num: u32 = start_date.month();
-    let mut year = start_date.year();
-    let mut days_in_month: Vec<i64> = vec![get_days_in_month(year, month_num)];
-
-    let mut last_month_idx = 0;
-    for i in 1..duration_days {
-        let last_month_name = String::from(months.get(last_month_idx).unwrap());
-        let month_name = format!("{}", (start_date + Duration::days(i)).format("%B %Y"));
-        if month_name != last_month_name {
-            month_num = month_num + 1;
-            if month_num > 12 {
-                month_num = 1;
-                year = year + 1;
-            }
-
-            last_month_idx = last_month_idx + 1;
-            months.push(String::from(&month_name));
-            days_in_month.push(get_days_in_month(year, month_num));
+    if args.strict {
+        for diagnostic in &mut parse_diagnostics {
+            diagnostic.severity = PlaSeverity::Error;
         }
     }
 
-    let stylings = r#"
-        body {
-            font-family: sans-serif;
-            font-size: 10pt;
-        }
-
-        table {
-            border-collapse: collapse;
-            position: relative;
-            border-spacing: 0;
-        }
-
-        .monthName {
-            text-align: center;
-            font-size: 32pt;
-            font-family: sans-serif;
-        }
-
-        td.emptyCell {
-            background-color: white !important;
-            border: none !important;
-        }
-
-        td.beerTitle {
-            font-size: 14pt;
-            font-family: sans-serif;
-            padding-right: 1rem;
-            width: 200px;
-        }
-
-        .beerTitle-spacer {
-            padding-top: 1rem;
-            padding-bottom: 1rem;
-        }
+    for diagnostic in &parse_diagnostics {
+        eprintln!("{}\n", diagnostic);
+    }
 
-        td.day {
-            border-spacing: 0;
+    if args.check {
+        let diagnostics = pla_parser.check();
+        for diagnostic in &diagnostics {
+            eprintln!("{}\n", diagnostic);
         }
 
-        td.dayOfMonth {
-          border-bottom: 1px solid black;
+        let parse_failed = parse_diagnostics.iter().any(|d| d.severity == PlaSeverity::Error);
+        if parse_failed || !diagnostics.is_empty() {
+            process::exit(1);
         }
 
-        td.lastDayOfMonth:not(:last-child) {
-            border-right: 3px solid black
-        }
+        println!("{}: no violations found", args.input_file);
+        return;
+    }
 
-        td:not(.monthName):not(.headerRow):nth-child(2n) {
-            background-color: lightgray;
-        }
+    // Compute when every entry, including parents without their own start, takes place
+    let pla_schedule = match pla_parser.schedule_in(&args.timezone) {
+        Ok(s) => s,
+        Err(why) => {
+            eprintln!("{}", PlaDiagnostic::error(&why.to_string()));
+            process::exit(1);
+        },
+    };
 
-        td.headerRow:nth-child(2n+1) {
-            background-color: lightgray;
-            position: relative;
-            overflow: visible;
-        }
+    let output_file = args.output_file.clone().unwrap_or_default();
+    let path = Path::new(&output_file);
+    let display = path.display();
 
-        div.full-bubble {
-          position: absolute;
-          top: calc(50% - 13px);
-          border-radius: 4px;
-          border: 1px solid darkgray;
-          background-color: lightgreen;
-          padding: .25rem;
-          z-index: 25;
-        }
+    let actual = match args.format.unwrap_or_else(|| Format::from_path(path)) {
+        #[cfg(feature = "serde")]
+        Format::Json => PlaDocument::new(&pla_parser.entries, &pla_schedule, &args.timezone).to_json(),
+        #[cfg(feature = "serde")]
+        Format::Yaml => PlaDocument::new(&pla_parser.entries, &pla_schedule, &args.timezone).to_yaml(),
+        format => render_chart(&args, &pla_parser, &pla_schedule, format),
+    };
 
-        div.spacer {
-          width: 43px;
-          padding: 0;
-          margin: 0;
-          text-align: center;
-        }
-    "#;
-
-    let num_months = months.len();
-    let process_length = 22;
-    let actual = format!("{}", html! {
-        : doctype::HTML;
-        html {
-            head {
-                title : format_args!("{} - {}", months.get(0).unwrap(), months.get(months.len() - 1).unwrap());
-                style: format_args!("{}", stylings);
-            }
-            body {
-                table {
-                    tr {
-                        td(class="emptyCell") {}
-                        td(class="emptyCell") {}
-                        @ for i in 0..num_months {
-                            td (class="monthName", colspan=format!("{}", days_in_month.get(i).unwrap())) {
-                                : format_args!("{}", months.get(i).unwrap());
-                            }
-                        }
-                    }
-
-                    tr {
-                        td(class="emptyCell") {}
-                        td(class="emptyCell") {}
-
-                        @ for i in 0..num_months {
-                            @ for j in 1..(*days_in_month.get(i).unwrap() as u32) + 1 {
-                                td(class = if j == (*days_in_month.get(i).unwrap() as u32) { "day lastDayOfMonth dayOfMonth" } else { "dayOfMonth" }) {
-                                    div (class="spacer") {
-                                        : format_args!("{}", j);
-                                    }
-                                }
-                            }
-                        }
-                    }
-
-                    tr {
-                        td(class="beerTitle", colspan="2") {
-                            div(class="beerTitle-spacer") {
-                                : "Autumn's"
-                            }
-                        }
-
-                        @ for i in 0..num_months {
-                            @ for j in 1..(*days_in_month.get(i).unwrap() as u32) + 1 {
-                                td(class="day headerRow") {
-                                    @if i == 0 && j == 12 {
-                                        div(class="full-bubble", style=format!("width: {}px", process_length * 45 - 6)) {
-                                            : "Autumn's Early Arrival Blonde"
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+    // Write to standard output, or to a file
+    if output_file == STDIO {
+        // Whatever is reading the chart may stop early, e.g. `head`, which isn't a failure
+        match io::stdout().write_all(actual.as_bytes()) {
+            Err(why) if why.kind() != io::ErrorKind::BrokenPipe => panic!("couldn't write to standard output: {}", why),
+            _ => {},
         }
-    });
 
-    // Write to a file for output
-    let path = Path::new(&args.output_file);
-    let display = path.display();
+        return;
+    }
 
     // Open a file in write-only mode, returns `io::Result<File>`
-    let mut file = match File::create(&path) {
+    let mut file = match File::create(path) {
         Err(why) => panic!("couldn't create {}: {}", display, why),
         Ok(file) => file,
     };
@@ -210,21 +247,66 @@ fn main() {
         Err(why) => panic!("couldn't write to {}: {}", display, why),
         Ok(_) => println!("successfully wrote to {}", display),
     }
-    // println!("{}", actual);
 }
 
-fn get_days_in_month(year: i32, month: u32) -> i64 {
-    NaiveDate::from_ymd(
-        match month {
-            12 => year + 1,
-            _ => year,
+/// Read the entries of a schedule document in `format`. As with pla input, checking and lenient
+/// parsing keep going past entries that can't be read.
+#[cfg(feature = "serde")]
+fn read_document(args: &Args, format: InputFormat) -> Result<(PlaParser, Vec<PlaDiagnostic>), PlaError> {
+    let mut text = String::new();
+    if args.input_file == STDIO {
+        io::stdin().read_to_string(&mut text)?;
+    } else {
+        File::open(&args.input_file)?.read_to_string(&mut text)?;
+    }
+
+    let document = match format {
+        InputFormat::Json => PlaDocument::from_json(&text),
+        _ => PlaDocument::from_yaml(&text),
+    };
+    let document = match document {
+        Ok(d) => d,
+        Err(e) if args.input_file == STDIO => return Err(e),
+        Err(e) => return Err(e.with_file(&args.input_file)),
+    };
+
+    if args.check || args.lenient {
+        Ok(PlaParser::from_document_lenient(&document))
+    } else {
+        PlaParser::from_document(&document).map(|p| (p, vec![]))
+    }
+}
+
+/// Draw `pla_parser`'s entries as a chart in `format`, which is HTML or SVG.
+fn render_chart(args: &Args, pla_parser: &PlaParser, pla_schedule: &PlaSchedule, format: Format) -> String {
+    let rows: Vec<GanttRow> = match args.view {
+        View::Tasks => {
+            // Arrange entries by their child blocks
+            let pla_tree = match pla_parser.build_tree() {
+                Ok(t) => t,
+                Err(why) => {
+                    eprintln!("{}", PlaDiagnostic::error(&why.to_string()));
+                    process::exit(1);
+                },
+            };
+
+            task_rows(&pla_tree, pla_schedule)
         },
-        match month {
-            12 => 1,
-            _ => month + 1,
+        View::Resources => resource_rows(&pla_parser.entries, pla_schedule),
+    };
+
+    // Resources are drawn once per entry that uses them, so arrows would be ambiguous there
+    let links: Vec<GanttLink> = match args.view {
+        View::Tasks => dependency_links(&pla_parser.entries, &rows),
+        View::Resources => vec![],
+    };
+
+    // Draw the chart with the grid
+    match format {
+        Format::Svg => SvgRenderer::new(&rows).with_links(&links).render(),
+        _ => {
+            let renderer = HtmlRenderer::new(&rows).with_links(&links);
+            if args.tooltips { renderer.with_tooltips().render() } else { renderer.render() }
         },
-        1,
-    )
-    .signed_duration_since(NaiveDate::from_ymd(year, month, 1))
-    .num_days()
-}
\ No newline at end of file
+    }
+}
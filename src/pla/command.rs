@@ -1,28 +1,51 @@
+use std::fmt::{Display, Formatter};
 use std::str::FromStr;
 use crate::pla::error::PlaParseError;
 
+/// The commands of the pla format, as described at https://www.arpalert.org/pla.html. Every line of
+/// a pla file is either an entry header, `[id] description`, or one of these commands within the
+/// entry above it:
+///
+/// - `child <id>` nests another entry beneath this one
+/// - `color <name or #rrggbb>` colours the entry's bar
+/// - `dep <id>` waits for another entry to finish before this one starts
+/// - `duration <hours or lengths such as 1w 2d>` is how long the entry takes
+/// - `progress <percent>` is how much of the entry is complete, which isn't part of the upstream
+///   format
+/// - `res <name>` books a resource for the length of the entry
+/// - `start <yyyy-mm-dd> [hour or hh:mm] [zone]` is when the entry starts
+///
+/// Words are separated by any whitespace, including tabs. Comments start with `#` or `//`, either
+/// on a line of their own or after whitespace at the end of a line. A `#` at the end of a line must
+/// be followed by whitespace, as colours start with one. The full grammar is in `docs/pla.bnf`.
 #[derive(Clone, PartialEq, Debug)]
 pub enum PlaCommand {
     CHILD,
+    COLOR,
     DEPENDENCY,
     DURATION,
     ENTRY,
+    PROGRESS,
     RESOURCE,
     START,
     UNKNOWN,
 }
 
-impl ToString for PlaCommand {
-    fn to_string(&self) -> String {
-        match self {
-            PlaCommand::CHILD => String::from("child"),
-            PlaCommand::DEPENDENCY => String::from("dep"),
-            PlaCommand::DURATION => String::from("duration"),
-            PlaCommand::ENTRY => String::from("entry"),
-            PlaCommand::RESOURCE => String::from("res"),
-            PlaCommand::START => String::from("start"),
-            _ => String::from("unknown"),
-        }
+impl Display for PlaCommand {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let command_str = match self {
+            PlaCommand::CHILD => "child",
+            PlaCommand::COLOR => "color",
+            PlaCommand::DEPENDENCY => "dep",
+            PlaCommand::DURATION => "duration",
+            PlaCommand::ENTRY => "entry",
+            PlaCommand::PROGRESS => "progress",
+            PlaCommand::RESOURCE => "res",
+            PlaCommand::START => "start",
+            _ => "unknown",
+        };
+
+        write!(f, "{}", command_str)
     }
 }
 
@@ -32,9 +55,11 @@ impl FromStr for PlaCommand {
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
             "child" => Ok(PlaCommand::CHILD),
+            "color" => Ok(PlaCommand::COLOR),
             "dep" => Ok(PlaCommand::DEPENDENCY),
             "duration" => Ok(PlaCommand::DURATION),
             "entry" => Ok(PlaCommand::ENTRY),
+            "progress" => Ok(PlaCommand::PROGRESS),
             "res" => Ok(PlaCommand::RESOURCE),
             "start" => Ok(PlaCommand::START),
             _ => Ok(PlaCommand::UNKNOWN),
@@ -42,6 +67,63 @@ impl FromStr for PlaCommand {
     }
 }
 
+impl PlaCommand {
+    /// Commands that can appear as blocks beneath an entry.
+    pub const SUB_BLOCK_COMMANDS: [PlaCommand; 7] = [
+        PlaCommand::CHILD,
+        PlaCommand::COLOR,
+        PlaCommand::DEPENDENCY,
+        PlaCommand::DURATION,
+        PlaCommand::PROGRESS,
+        PlaCommand::RESOURCE,
+        PlaCommand::START,
+    ];
+
+    /// The block command spelled most like `name`, if one is close enough that `name` is likely
+    /// a typo of it.
+    pub fn suggest(name: &str) -> Option<PlaCommand> {
+        let max_distance = (name.chars().count() / 3).max(1);
+
+        PlaCommand::SUB_BLOCK_COMMANDS
+            .iter()
+            .map(|c| (edit_distance(name, &c.to_string()), c))
+            .filter(|(distance, _)| *distance <= max_distance)
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, c)| c.clone())
+    }
+}
+
+/// Number of single character insertions, deletions, substitutions and swaps of adjacent
+/// characters needed to turn `a` into `b`.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+
+    // distances[i][j] is the distance between the first i characters of a and the first j of b
+    let mut distances: Vec<Vec<usize>> = vec![vec![0; b.len() + 1]; a.len() + 1];
+    for (i, row) in distances.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    distances[0] = (0..=b.len()).collect();
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (distances[i - 1][j] + 1)
+                .min(distances[i][j - 1] + 1)
+                .min(distances[i - 1][j - 1] + cost);
+
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(distances[i - 2][j - 2] + 1);
+            }
+
+            distances[i][j] = distance;
+        }
+    }
+
+    distances[a.len()][b.len()]
+}
+
 #[cfg(test)]
 mod tests {
     use crate::pla::command::PlaCommand;
@@ -49,12 +131,14 @@ mod tests {
 
     #[test]
     fn it_should_convert_a_command_to_a_string() {
-        let strings = vec!["child", "dep", "duration", "entry", "res", "start", "unknown"];
-        let commands = vec![
+        let strings = ["child", "color", "dep", "duration", "entry", "progress", "res", "start", "unknown"];
+        let commands = [
             PlaCommand::CHILD,
+            PlaCommand::COLOR,
             PlaCommand::DEPENDENCY,
             PlaCommand::DURATION,
             PlaCommand::ENTRY,
+            PlaCommand::PROGRESS,
             PlaCommand::RESOURCE,
             PlaCommand::START,
             PlaCommand::UNKNOWN
@@ -68,12 +152,14 @@ mod tests {
 
     #[test]
     fn it_should_convert_a_command_from_a_string() {
-        let strings = vec!["child", "dep", "duration", "entry", "res", "start", "wakka"];
-        let commands = vec![
+        let strings = ["child", "color", "dep", "duration", "entry", "progress", "res", "start", "wakka"];
+        let commands = [
             PlaCommand::CHILD,
+            PlaCommand::COLOR,
             PlaCommand::DEPENDENCY,
             PlaCommand::DURATION,
             PlaCommand::ENTRY,
+            PlaCommand::PROGRESS,
             PlaCommand::RESOURCE,
             PlaCommand::START,
             PlaCommand::UNKNOWN
@@ -83,4 +169,15 @@ mod tests {
             assert_eq!(commands[i], PlaCommand::from_str(strings[i]).unwrap());
         }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn it_should_suggest_a_command_for_a_typo() {
+        assert_eq!(Some(PlaCommand::DURATION), PlaCommand::suggest("durtion"));
+        assert_eq!(Some(PlaCommand::START), PlaCommand::suggest("strat"));
+        assert_eq!(Some(PlaCommand::CHILD), PlaCommand::suggest("chidl"));
+        assert_eq!(Some(PlaCommand::RESOURCE), PlaCommand::suggest("rse"));
+        assert_eq!(Some(PlaCommand::COLOR), PlaCommand::suggest("colour"));
+        assert_eq!(None, PlaCommand::suggest("wakka"));
+        assert_eq!(None, PlaCommand::suggest("foo"));
+    }
+}
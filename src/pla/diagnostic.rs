@@ -0,0 +1,190 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use crate::pla::error::{PlaError, PlaParseError};
+
+/// Number of columns a tab in the source is drawn as.
+const TAB_WIDTH: usize = 4;
+
+/// How serious a diagnostic is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaSeverity {
+    Error,
+    Warning,
+}
+
+impl Display for PlaSeverity {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaSeverity::Error => write!(f, "error"),
+            PlaSeverity::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem with a pla file, located within its source so that it can be shown to whoever
+/// wrote it. Displays in the style of rustc, with a caret beneath the offending text.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaDiagnostic {
+    pub severity: PlaSeverity,
+    pub message: String,
+    pub file: Option<String>,
+
+    /// 1-based line of the problem, or 0 if it can't be attributed to a single line.
+    pub line_number: usize,
+
+    /// 1-based column of the first character of the offending text.
+    pub column: usize,
+
+    /// Number of characters of offending text.
+    pub length: usize,
+
+    /// The whole line the problem occurred on, as it appears in the file.
+    pub source_line: String,
+}
+
+impl PlaDiagnostic {
+    pub fn error(message: &str) -> PlaDiagnostic {
+        PlaDiagnostic {
+            severity: PlaSeverity::Error,
+            message: String::from(message),
+            file: None,
+            line_number: 0,
+            column: 0,
+            length: 0,
+            source_line: String::from(""),
+        }
+    }
+
+    /// The same diagnostic, pointing at `length` characters from `column` of `source_line`, which
+    /// is line `line_number` of the file.
+    pub fn at(self, line_number: usize, column: usize, length: usize, source_line: &str) -> PlaDiagnostic {
+        PlaDiagnostic {
+            line_number,
+            column,
+            length,
+            source_line: String::from(source_line),
+            ..self
+        }
+    }
+
+    pub fn with_file(self, file: &str) -> PlaDiagnostic {
+        PlaDiagnostic {
+            file: Some(String::from(file)),
+            ..self
+        }
+    }
+
+    /// Width that the first `count` characters of the source line take up once tabs are expanded.
+    /// Columns past the end of the line are a single character wide.
+    fn display_width(&self, count: usize) -> usize {
+        let past_end = count.saturating_sub(self.source_line.chars().count());
+        let width: usize = self.source_line
+            .chars()
+            .take(count)
+            .map(|c| if c == '\t' { TAB_WIDTH } else { 1 })
+            .sum();
+
+        width + past_end
+    }
+}
+
+impl From<&PlaParseError> for PlaDiagnostic {
+    fn from(e: &PlaParseError) -> Self {
+        let diagnostic = PlaDiagnostic::error(&e.message)
+            .at(e.line_number, e.column, e.text.chars().count(), &e.source_line);
+
+        match &e.file {
+            Some(file) => diagnostic.with_file(file),
+            None => diagnostic,
+        }
+    }
+}
+
+impl From<&PlaError> for PlaDiagnostic {
+    fn from(e: &PlaError) -> Self {
+        match e {
+            PlaError::Syntax(e) | PlaError::Semantic(e) => PlaDiagnostic::from(e),
+            PlaError::Io(_) => PlaDiagnostic::error(&format!("{}", e)),
+        }
+    }
+}
+
+impl Display for PlaDiagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.severity, self.message)?;
+        if self.line_number == 0 {
+            return Ok(());
+        }
+
+        let file = self.file.as_deref().unwrap_or("<input>");
+        let gutter = " ".repeat(self.line_number.to_string().len());
+        write!(f, "\n{}--> {}:{}:{}", gutter, file, self.line_number, self.column)?;
+        if self.source_line.is_empty() {
+            return Ok(());
+        }
+
+        // Missing text, e.g. a `duration` without a number, is pointed at with a single caret.
+        let start = self.column.saturating_sub(1);
+        let offset = self.display_width(start);
+        let carets = (self.display_width(start + self.length) - offset).max(1);
+        let source_line = self.source_line.trim_end().replace('\t', &" ".repeat(TAB_WIDTH));
+
+        write!(f, "\n{} |", gutter)?;
+        write!(f, "\n{} | {}", self.line_number, source_line)?;
+        write!(f, "\n{} | {}{}", gutter, " ".repeat(offset), "^".repeat(carets))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pla::diagnostic::{PlaDiagnostic, PlaSeverity};
+    use crate::pla::error::PlaParseError;
+
+    #[test]
+    fn it_should_display_a_diagnostic_with_a_caret_under_the_offending_text() {
+        let diagnostic = PlaDiagnostic::error("unable to parse date from string")
+            .at(12, 11, 10, "    start 2021-13-40 10")
+            .with_file("schedule.pla");
+
+        assert_eq!(r#"error: unable to parse date from string
+  --> schedule.pla:12:11
+   |
+12 |     start 2021-13-40 10
+   |           ^^^^^^^^^^"#, format!("{}", diagnostic));
+    }
+
+    #[test]
+    fn it_should_expand_tabs_before_the_offending_text() {
+        let diagnostic = PlaDiagnostic::error("Cannot parse duration for duration command as u32")
+            .at(3, 11, 3, "\tduration six");
+
+        assert_eq!(r#"error: Cannot parse duration for duration command as u32
+ --> <input>:3:11
+  |
+3 |     duration six
+  |              ^^^"#, format!("{}", diagnostic));
+    }
+
+    #[test]
+    fn it_should_display_a_diagnostic_without_a_line() {
+        let diagnostic = PlaDiagnostic {
+            severity: PlaSeverity::Warning,
+            ..PlaDiagnostic::error("entries depend on themselves: 1 -> 2 -> 1")
+        };
+
+        assert_eq!("warning: entries depend on themselves: 1 -> 2 -> 1", format!("{}", diagnostic));
+    }
+
+    #[test]
+    fn it_should_point_at_missing_text_with_a_single_caret() {
+        let error = PlaParseError::new("unable to parse duration length for duration command")
+            .at(2, 14, "")
+            .with_source_line("    duration");
+
+        assert_eq!(r#"error: unable to parse duration length for duration command
+ --> <input>:2:14
+  |
+2 |     duration
+  |              ^"#, format!("{}", PlaDiagnostic::from(&error)));
+    }
+}
@@ -0,0 +1,455 @@
+use chrono::{NaiveDateTime, Timelike};
+use serde::{Deserialize, Serialize};
+use crate::pla::command::PlaCommand;
+use crate::pla::entry::{PlaEntry, PlaNote};
+use crate::pla::error::{PlaError, PlaParseError};
+use crate::pla::lexer::{tokenize, PlaTokenKind};
+use crate::pla::schedule::PlaSchedule;
+use crate::pla::sub_blocks::PlaSubBlock;
+use crate::pla::time_zone::PlaTimeZone;
+
+/// Version of the document schema described in `docs/schedule.md`. Fields may be added without
+/// changing it, but any other change to the schema comes with a new version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// A resolved schedule as data, for writing as JSON or YAML. Every entry is listed in the order
+/// it was written, along with when it takes place once parents and dependent entries have been
+/// scheduled. Documents can also be read back, from this tool or any other that writes the same
+/// schema, as the entries they describe.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaDocument {
+    pub version: u32,
+
+    /// Time zone that the `start` and `end` of every entry are given in.
+    pub time_zone: PlaTimeZone,
+    pub entries: Vec<PlaDocumentEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaDocumentEntry {
+    pub id: u32,
+
+    #[serde(default)]
+    pub description: String,
+
+    /// When the entry takes place, or `None` if it can't be scheduled.
+    #[serde(default)]
+    pub start: Option<NaiveDateTime>,
+    #[serde(default)]
+    pub end: Option<NaiveDateTime>,
+
+    /// The entry's own `start` block, if it has one.
+    #[serde(default)]
+    pub fixed_start: Option<PlaDocumentStart>,
+
+    /// Length of the entry's own `duration` block, in minutes.
+    #[serde(default)]
+    pub duration_minutes: Option<i64>,
+
+    #[serde(default)]
+    pub resources: Vec<String>,
+    #[serde(default)]
+    pub dependencies: Vec<u32>,
+    #[serde(default)]
+    pub children: Vec<u32>,
+    #[serde(default)]
+    pub color: Option<String>,
+    #[serde(default)]
+    pub progress: Option<u32>,
+
+    /// Comments written about the entry and its blocks.
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
+
+/// A `start` block: a time as written and, if it was written with one, its time zone.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaDocumentStart {
+    pub time: NaiveDateTime,
+
+    #[serde(default)]
+    pub zone: Option<PlaTimeZone>,
+}
+
+impl PlaDocument {
+    /// A document for `entries`, as scheduled in `zone` by `schedule`.
+    pub fn new(entries: &[PlaEntry], schedule: &PlaSchedule, zone: &PlaTimeZone) -> PlaDocument {
+        PlaDocument {
+            version: SCHEMA_VERSION,
+            time_zone: *zone,
+            entries: entries.iter().map(|e| PlaDocumentEntry::new(e, schedule)).collect(),
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        // Every field is a string, number or list of them, which can always be written.
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    pub fn to_yaml(&self) -> String {
+        serde_yaml::to_string(self).unwrap()
+    }
+
+    pub fn from_json(text: &str) -> Result<PlaDocument, PlaError> {
+        serde_json::from_str(text)
+            .map_err(|e| PlaDocument::syntax_error(text, &e.to_string(), e.line(), e.column()))
+    }
+
+    pub fn from_yaml(text: &str) -> Result<PlaDocument, PlaError> {
+        serde_yaml::from_str(text).map_err(|e| {
+            let (line_number, column) = e.location().map_or((0, 0), |l| (l.line(), l.column()));
+            PlaDocument::syntax_error(text, &e.to_string(), line_number, column)
+        })
+    }
+
+    /// An error at `column` of `line_number` of `text`, which is 0 if the position isn't known.
+    fn syntax_error(text: &str, message: &str, line_number: usize, column: usize) -> PlaError {
+        // Both libraries end their messages with the position, which the error already holds
+        let message = message.rsplit_once(" at line ").map_or(message, |(m, _)| m);
+        let error = PlaParseError::new(message).at(line_number, column, "");
+
+        match line_number.checked_sub(1).and_then(|idx| text.split('\n').nth(idx)) {
+            Some(l) => PlaError::Syntax(error.with_source_line(l)),
+            None => PlaError::Syntax(error),
+        }
+    }
+
+    /// The entries the document describes, and an error for each entry or value that would have
+    /// been rejected had it been written in a pla file. Entries and blocks with errors are
+    /// skipped. Resolved times are ignored, as they're computed from the entries again.
+    pub fn to_entries(&self) -> (Vec<PlaEntry>, Vec<PlaError>) {
+        if self.version > SCHEMA_VERSION {
+            let message = format!("unsupported document version {}, expected {} or earlier", self.version, SCHEMA_VERSION);
+            return (vec![], vec![PlaError::Semantic(PlaParseError::new(&message))]);
+        }
+
+        let mut entries: Vec<PlaEntry> = vec![];
+        let mut errors: Vec<PlaError> = vec![];
+        for document_entry in &self.entries {
+            if entries.iter().any(|e| e.id == document_entry.id) {
+                let message = format!("entry {} is defined more than once", document_entry.id);
+                errors.push(PlaError::Semantic(PlaParseError::new(&message)));
+                continue;
+            }
+
+            // Text that can't be written back out as it is would be lost when the entries are
+            // next written as pla, so it's rejected rather than kept until then.
+            let header = format!("[{}] {}", document_entry.id, document_entry.description);
+            if let Some(message) = get_text_error(&header) {
+                let message = format!("entry {}, `{}`: {}", document_entry.id, header, message);
+                errors.push(PlaError::Semantic(PlaParseError::new(&message)));
+                continue;
+            }
+
+            let mut blocks: Vec<PlaSubBlock> = vec![];
+            for line in document_entry.block_lines() {
+                if let Some(message) = get_text_error(&line) {
+                    let message = format!("entry {}, `{}`: {}", document_entry.id, line, message);
+                    errors.push(PlaError::Semantic(PlaParseError::new(&message)));
+                    continue;
+                }
+
+                match PlaSubBlock::try_from((document_entry.id, line.as_str())) {
+                    Ok(block) => blocks.push(block),
+                    Err(e) => {
+                        let message = format!("entry {}, `{}`: {}", document_entry.id, line, e.message);
+                        errors.push(PlaError::Syntax(PlaParseError::new(&message)));
+                    },
+                }
+            }
+
+            let mut entry = document_entry.to_entry(blocks);
+            entry.notes.retain(|note| {
+                let multi_line = note.text.contains(['\n', '\r']);
+                if multi_line {
+                    let message = format!("entry {}: a note can't span more than one line", document_entry.id);
+                    errors.push(PlaError::Semantic(PlaParseError::new(&message)));
+                }
+                !multi_line
+            });
+
+            entries.push(entry);
+        }
+
+        (entries, errors)
+    }
+}
+
+/// Why `line` of pla text wouldn't be read back as it was written, if it wouldn't: part of it
+/// would be a comment, or it would be split over several lines.
+fn get_text_error(line: &str) -> Option<String> {
+    if line.contains(['\n', '\r']) {
+        return Some(String::from("text can't span more than one line"));
+    }
+
+    tokenize(line, 0)
+        .into_iter()
+        .find(|t| t.kind == PlaTokenKind::Comment)
+        .map(|t| {
+            let comment: String = line.chars().skip(t.span.start.column - 1).collect();
+            format!("`{}` would be read as a comment", comment)
+        })
+}
+
+impl PlaDocumentEntry {
+    fn new(entry: &PlaEntry, schedule: &PlaSchedule) -> PlaDocumentEntry {
+        let time = schedule.get(entry.id);
+
+        PlaDocumentEntry {
+            id: entry.id,
+            description: String::from(&entry.description),
+            start: time.map(|t| t.start),
+            end: time.map(|t| t.end),
+            fixed_start: entry.start().map(|s| PlaDocumentStart { time: s.time, zone: s.zone }),
+            duration_minutes: entry.duration().map(|d| d.duration.num_minutes()),
+            resources: entry.resources().iter().map(|r| String::from(&r.resource_name)).collect(),
+            dependencies: entry.dependencies().iter().map(|d| d.dependency_id).collect(),
+            children: entry.children().iter().map(|c| c.child_id).collect(),
+            color: entry.get_color(),
+            progress: entry.get_progress(),
+            notes: entry.notes.iter().map(|n| String::from(&n.text)).collect(),
+        }
+    }
+
+    /// The entry, without the blocks that its fields stand for.
+    fn to_entry(&self, blocks: Vec<PlaSubBlock>) -> PlaEntry {
+        PlaEntry {
+            id: self.id,
+            description: String::from(&self.description),
+            line_number: 0,
+            notes: self.notes.iter().map(|n| PlaNote { text: String::from(n), line_number: 0, subject_line_number: 0 }).collect(),
+            blocks,
+        }
+    }
+
+    /// Every block the entry's fields stand for, as it would be written in a pla file, so that
+    /// they can be checked as blocks in a file are.
+    fn block_lines(&self) -> Vec<String> {
+        let mut lines: Vec<String> = vec![];
+        if let Some(start) = &self.fixed_start {
+            // Seconds can't be written in pla, but are kept for the parser to reject
+            let whole_minute = start.time.second() == 0 && start.time.nanosecond() == 0;
+            let format = if whole_minute { "%Y-%m-%d %H:%M" } else { "%Y-%m-%d %H:%M:%S%.f" };
+            let line = format!("{} {}", PlaCommand::START, start.time.format(format));
+            lines.push(match &start.zone {
+                Some(zone) => format!("{} {}", line, zone),
+                None => line,
+            });
+        }
+
+        lines.extend(self.duration_minutes.map(|m| format!("{} {}m", PlaCommand::DURATION, m)));
+        lines.extend(self.resources.iter().map(|r| format!("{} {}", PlaCommand::RESOURCE, r)));
+        lines.extend(self.dependencies.iter().map(|d| format!("{} {}", PlaCommand::DEPENDENCY, d)));
+        lines.extend(self.children.iter().map(|c| format!("{} {}", PlaCommand::CHILD, c)));
+        lines.extend(self.color.iter().map(|c| format!("{} {}", PlaCommand::COLOR, c)));
+        lines.extend(self.progress.map(|p| format!("{} {}", PlaCommand::PROGRESS, p)));
+
+        lines
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::pla::document::PlaDocument;
+    use crate::pla::error::PlaError;
+    use crate::pla::parser::PlaParser;
+    use crate::pla::time_zone::PlaTimeZone;
+
+    fn document() -> PlaDocument {
+        let pla_parser: PlaParser = r#"[10000] Autumn's Early Arrival Blonde
+    child 89
+    child 90
+
+[89] Brewing Process # first batch
+    start 2020-10-20 16 Europe/London
+    duration 6
+    res Mash Tun
+    color gold
+
+[90] Carbonation
+    dep 89
+    duration 2
+    progress 50
+"#.parse().unwrap();
+
+        let zone = PlaTimeZone::UTC;
+        PlaDocument::new(&pla_parser.entries, &pla_parser.schedule_in(&zone).unwrap(), &zone)
+    }
+
+    #[test]
+    fn it_should_resolve_when_every_entry_takes_place() {
+        let document = document();
+        assert_eq!(3, document.entries.len());
+
+        let parent = &document.entries[0];
+        assert_eq!(vec![89, 90], parent.children);
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(15, 0, 0)), parent.start);
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(23, 0, 0)), parent.end);
+        assert_eq!(None, parent.fixed_start);
+
+        let carbonation = &document.entries[2];
+        assert_eq!(vec![89], carbonation.dependencies);
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(21, 0, 0)), carbonation.start);
+        assert_eq!(Some(120), carbonation.duration_minutes);
+        assert_eq!(Some(50), carbonation.progress);
+    }
+
+    #[test]
+    fn it_should_write_the_documented_schema() {
+        let json: serde_json::Value = serde_json::from_str(&document().to_json()).unwrap();
+        assert_eq!(serde_json::json!({
+            "version": 1,
+            "time_zone": "UTC",
+            "entries": [
+                {
+                    "id": 10000,
+                    "description": "Autumn's Early Arrival Blonde",
+                    "start": "2020-10-20T15:00:00",
+                    "end": "2020-10-20T23:00:00",
+                    "fixed_start": null,
+                    "duration_minutes": null,
+                    "resources": [],
+                    "dependencies": [],
+                    "children": [89, 90],
+                    "color": null,
+                    "progress": null,
+                    "notes": [],
+                },
+                {
+                    "id": 89,
+                    "description": "Brewing Process",
+                    "start": "2020-10-20T15:00:00",
+                    "end": "2020-10-20T21:00:00",
+                    "fixed_start": { "time": "2020-10-20T16:00:00", "zone": "Europe/London" },
+                    "duration_minutes": 360,
+                    "resources": ["Mash Tun"],
+                    "dependencies": [],
+                    "children": [],
+                    "color": "gold",
+                    "progress": null,
+                    "notes": ["first batch"],
+                },
+                {
+                    "id": 90,
+                    "description": "Carbonation",
+                    "start": "2020-10-20T21:00:00",
+                    "end": "2020-10-20T23:00:00",
+                    "fixed_start": null,
+                    "duration_minutes": 120,
+                    "resources": [],
+                    "dependencies": [89],
+                    "children": [],
+                    "color": null,
+                    "progress": 50,
+                    "notes": [],
+                },
+            ],
+        }), json);
+    }
+
+    #[test]
+    fn it_should_write_the_same_document_as_yaml() {
+        let document = document();
+        let from_yaml: PlaDocument = serde_yaml::from_str(&document.to_yaml()).unwrap();
+        assert_eq!(document, from_yaml);
+    }
+
+    #[test]
+    fn it_should_read_back_the_entries_a_document_was_written_from() {
+        let pla_parser: PlaParser = r#"[89] Brewing Process # first batch
+    start 2020-10-20 16:30 Europe/London
+    duration 90m
+    res Mash Tun
+    color #a0c8ff
+
+[90] Carbonation
+    duration 2
+    dep 89
+    progress 50
+"#.parse().unwrap();
+
+        let zone = PlaTimeZone::UTC;
+        let json = PlaDocument::new(&pla_parser.entries, &pla_parser.schedule_in(&zone).unwrap(), &zone).to_json();
+        let imported = PlaParser::from_document(&PlaDocument::from_json(&json).unwrap()).unwrap();
+
+        assert_eq!(format!("{:?}", pla_parser.entries), format!("{:?}", imported.entries));
+        assert_eq!(vec!["first batch"], imported.entries[0].get_notes().iter().map(|n| n.text.as_str()).collect::<Vec<&str>>());
+        assert_eq!(
+            pla_parser.schedule_in(&zone).unwrap().get(90),
+            imported.schedule_in(&zone).unwrap().get(90),
+        );
+    }
+
+    #[test]
+    fn it_should_check_imported_entries_as_pla_entries_are() {
+        let document = PlaDocument::from_yaml(r#"
+version: 1
+time_zone: UTC
+entries:
+  - id: 89
+    description: Brewing Process
+    duration_minutes: 360
+    progress: 150
+  - id: 90
+    fixed_start: { time: 2021-03-28T01:30:00, zone: Europe/London }
+    color: mauvish
+  - id: 89
+"#).unwrap();
+
+        let messages: Vec<String> = PlaParser::from_document_lenient(&document).1.iter().map(|d| d.message.clone()).collect();
+        assert_eq!(vec![
+            "entry 89, `progress 150`: progress must be a percentage from 0 to 100",
+            "entry 90, `start 2021-03-28 01:30 Europe/London`: clocks in Europe/London skip this time",
+            "entry 90, `color mauvish`: expected a colour name or `#rrggbb` for color command",
+            "entry 89 is defined more than once",
+        ], messages);
+
+        let (pla_parser, _) = PlaParser::from_document_lenient(&document);
+        assert_eq!(2, pla_parser.entries.len());
+        assert_eq!(Some(360), pla_parser.entries[0].duration().map(|d| d.duration.num_minutes()));
+        assert!(PlaParser::from_document(&document).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_imported_text_that_would_be_lost_when_written_as_pla() {
+        let document = PlaDocument::from_yaml(r#"
+version: 1
+time_zone: UTC
+entries:
+  - id: 89
+    description: "Mash // Sparge"
+  - id: 90
+    description: "Brew #1"
+    resources: ["Fermentor #3", "Keg # 2"]
+    notes: ["first batch", "fill\nslowly"]
+"#).unwrap();
+
+        let (imported, diagnostics) = PlaParser::from_document_lenient(&document);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.message.clone()).collect();
+        assert_eq!(vec![
+            "entry 89, `[89] Mash // Sparge`: `// Sparge` would be read as a comment",
+            "entry 90, `res Keg # 2`: `# 2` would be read as a comment",
+            "entry 90: a note can't span more than one line",
+        ], messages);
+
+        let written: PlaParser = imported.to_string().parse().unwrap();
+        assert_eq!(format!("{:?}", imported.entries), format!("{:?}", written.entries));
+        assert_eq!("Fermentor #3", written.entries[0].resources()[0].resource_name);
+        assert_eq!(vec!["first batch"], written.entries[0].get_notes().iter().map(|n| n.text.as_str()).collect::<Vec<&str>>());
+    }
+
+    #[test]
+    fn it_should_report_where_a_document_cant_be_read() {
+        let error = match PlaDocument::from_json("{\n  \"version\": 1,\n  \"entries\": [{ \"id\": \"89\" }]\n}") {
+            Err(PlaError::Syntax(e)) => e,
+            other => panic!("Expected a syntax error, got {:?}", other),
+        };
+        assert_eq!(3, error.line_number);
+        assert_eq!("  \"entries\": [{ \"id\": \"89\" }]", error.source_line);
+        assert!(error.message.starts_with("invalid type: string \"89\""), "{}", error.message);
+
+        let unsupported = PlaDocument::from_json(r#"{ "version": 2, "time_zone": "UTC", "entries": [] }"#).unwrap();
+        assert!(PlaParser::from_document(&unsupported).is_err());
+    }
+}
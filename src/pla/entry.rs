@@ -1,37 +1,141 @@
-use std::fmt::{Debug, Formatter};
-use crate::pla::sub_blocks::{PlaSubBlock};
+use std::fmt::{Debug, Display, Formatter};
+use chrono::{Duration, NaiveDateTime};
+use crate::pla::time_zone::PlaTimeZone;
+use crate::pla::sub_blocks::{PlaChildBlock, PlaDependencyBlock, PlaDurationBlock, PlaResourceBlock, PlaStartBlock, PlaSubBlock};
 
+/// A comment written in a pla file, kept with the entry it was written in.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaNote {
+    pub text: String,
+
+    /// 1-based line of the comment within the source file.
+    pub line_number: usize,
+
+    /// Line of the entry header or block that the comment describes.
+    pub subject_line_number: usize,
+}
+
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlaEntry {
     pub id: u32,
     pub description: String,
-    pub children: Option<Vec<Box<dyn PlaSubBlock>>>
+
+    /// 1-based line of the entry header within the source file, or 0 if the entry was not
+    /// parsed from a file.
+    pub line_number: usize,
+
+    /// Comments about the entry and its blocks.
+    pub notes: Vec<PlaNote>,
+
+    /// The lines within the entry, in the order they were written.
+    pub blocks: Vec<PlaSubBlock>,
 }
 
 impl PlaEntry {
-    pub fn has_children(&self) -> bool {
-        self.children.is_some()
+    pub fn has_blocks(&self) -> bool {
+        !self.blocks.is_empty()
     }
-}
 
-impl Clone for PlaEntry {
-    fn clone(&self) -> Self {
-        let cloned_children: Option<Vec<Box<dyn PlaSubBlock>>> = match self.children.as_ref() {
-            Some(c) => {
-                Some(c
-                    .iter()
-                    .map(|bsb| {
-                        bsb.clone()
-                    })
-                    .collect())
-            },
-            None => None
-        };
+    /// Comments about the entry itself, rather than one of its blocks.
+    pub fn get_notes(&self) -> Vec<&PlaNote> {
+        self.notes.iter().filter(|n| n.subject_line_number == self.line_number).collect()
+    }
 
-        PlaEntry {
-            id: self.id,
-            description: String::from(&self.description),
-            children: cloned_children,
+    /// Comments about `block`, which is one of this entry's blocks.
+    pub fn get_block_notes(&self, block: &PlaSubBlock) -> Vec<&PlaNote> {
+        // Blocks that weren't parsed from a file have no line, and can't have been commented on.
+        if block.get_line_number() == 0 {
+            return vec![];
         }
+
+        self.notes.iter().filter(|n| n.subject_line_number == block.get_line_number()).collect()
+    }
+
+    /// The entry's `start` block. Only the first is used if there are several.
+    pub fn start(&self) -> Option<&PlaStartBlock> {
+        self.blocks.iter().find_map(|b| match b {
+            PlaSubBlock::Start(start) => Some(start),
+            _ => None,
+        })
+    }
+
+    /// The entry's `duration` block. Only the first is used if there are several.
+    pub fn duration(&self) -> Option<&PlaDurationBlock> {
+        self.blocks.iter().find_map(|b| match b {
+            PlaSubBlock::Duration(duration) => Some(duration),
+            _ => None,
+        })
+    }
+
+    /// The resources the entry books.
+    pub fn resources(&self) -> Vec<&PlaResourceBlock> {
+        self.blocks.iter().filter_map(|b| match b {
+            PlaSubBlock::Resource(resource) => Some(resource),
+            _ => None,
+        }).collect()
+    }
+
+    /// The entries that must end before this one starts.
+    pub fn dependencies(&self) -> Vec<&PlaDependencyBlock> {
+        self.blocks.iter().filter_map(|b| match b {
+            PlaSubBlock::Dependency(dependency) => Some(dependency),
+            _ => None,
+        }).collect()
+    }
+
+    /// The entries nested beneath this one.
+    pub fn children(&self) -> Vec<&PlaChildBlock> {
+        self.blocks.iter().filter_map(|b| match b {
+            PlaSubBlock::Child(child) => Some(child),
+            _ => None,
+        }).collect()
+    }
+
+    /// The colour this entry's bar is drawn in, if it has a `color` sub block.
+    pub fn get_color(&self) -> Option<String> {
+        self.blocks.iter().find_map(|b| match b {
+            PlaSubBlock::Color(color) => Some(String::from(&color.color)),
+            _ => None,
+        })
+    }
+
+    /// Percentage of this entry that is complete, if it has a `progress` sub block.
+    pub fn get_progress(&self) -> Option<u32> {
+        self.blocks.iter().find_map(|b| match b {
+            PlaSubBlock::Progress(progress) => Some(progress.progress),
+            _ => None,
+        })
+    }
+
+    /// The moment this entry starts, if it has a `start` sub block, in UTC if it was written with
+    /// a time zone.
+    pub fn get_start_time(&self) -> Option<NaiveDateTime> {
+        self.get_start_time_in(&PlaTimeZone::UTC)
+    }
+
+    /// What clocks in `zone` read when this entry starts, if it has a `start` sub block whose
+    /// time can be converted to `zone`.
+    pub fn get_start_time_in(&self, zone: &PlaTimeZone) -> Option<NaiveDateTime> {
+        self.start().and_then(|s| s.get_time_in(zone))
+    }
+
+    /// Length of this entry. Entries without a `duration` sub block take no time.
+    pub fn get_length(&self) -> Duration {
+        self.duration().map_or(Duration::zero(), |d| d.duration)
+    }
+
+    /// The moment this entry ends, if it has a `start` sub block. Entries without a `duration`
+    /// end at the moment they start.
+    pub fn get_end_time(&self) -> Option<NaiveDateTime> {
+        self.get_end_time_in(&PlaTimeZone::UTC)
+    }
+
+    /// What clocks in `zone` read when this entry ends, if it has a `start` sub block and ends
+    /// before the latest time that can be represented.
+    pub fn get_end_time_in(&self, zone: &PlaTimeZone) -> Option<NaiveDateTime> {
+        self.get_start_time_in(zone).and_then(|s| s.checked_add_signed(self.get_length()))
     }
 }
 
@@ -41,38 +145,168 @@ impl PartialEq for PlaEntry {
     }
 }
 
-impl Debug for PlaEntry {
+/// Displays as the entry's header followed by each of its blocks on an indented line, in the form
+/// it would be written in a pla file. Notes are written as comments on the lines before whatever
+/// they describe.
+impl Display for PlaEntry {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let mut target_string = String::new();
-        use std::fmt::Write;
-        let children_str: String = match &self.children {
-            Some(x) => {
-                write!(target_string, "{:?}", x).unwrap();
-
-                target_string
-            },
-            None => String::from("None")
-        };
+        for note in self.get_notes() {
+            writeln!(f, "# {}", note.text)?;
+        }
+
+        if self.description.is_empty() {
+            writeln!(f, "[{}]", self.id)?;
+        } else {
+            writeln!(f, "[{}] {}", self.id, self.description)?;
+        }
+
+        for block in &self.blocks {
+            for note in self.get_block_notes(block) {
+                writeln!(f, "    # {}", note.text)?;
+            }
+            writeln!(f, "    {}", block)?;
+        }
+
+        Ok(())
+    }
+}
 
-        write!(f, "PlaEntry ({:?}), description: {:?}, children: {:?}", self.id, self.description, children_str)
+impl Debug for PlaEntry {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "PlaEntry ({:?}), description: {:?}, blocks: {:?}", self.id, self.description, self.blocks)
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use chrono::NaiveDate;
     use crate::pla::entry::PlaEntry;
+    use chrono::Duration;
+    use crate::pla::sub_blocks::{PlaChildBlock, PlaColorBlock, PlaDependencyBlock, PlaDurationBlock, PlaResourceBlock, PlaStartBlock, PlaSubBlock};
 
     #[test]
-    fn it_should_be_able_to_clone_an_entry_with_no_children() {
+    fn it_should_be_able_to_clone_an_entry_with_no_blocks() {
         let entry = PlaEntry {
             id: 196,
             description: "No Operation".to_string(),
-            children: None
+            line_number: 0,
+            notes: vec![],
+            blocks: vec![]
         };
 
         let cloned_entry = entry.clone();
         assert_eq!(196, cloned_entry.id);
         assert_eq!(String::from("No Operation"), cloned_entry.description);
-        assert!(cloned_entry.children.is_none());
+        assert!(!cloned_entry.has_blocks());
+    }
+
+    #[test]
+    fn it_should_compute_start_and_end_times_from_sub_blocks() {
+        let entry = PlaEntry {
+            id: 89,
+            description: "Brewing Process (Mash Tun 15G G10)".to_string(),
+            line_number: 0,
+            notes: vec![],
+            blocks: vec![
+                PlaSubBlock::from(PlaDurationBlock::try_from((89, "duration 6")).unwrap()),
+                PlaSubBlock::from(PlaStartBlock::try_from((89, "start 2020-10-20 16")).unwrap()),
+            ]
+        };
+
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(16, 0, 0)), entry.get_start_time());
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(22, 0, 0)), entry.get_end_time());
+    }
+
+    #[test]
+    fn it_should_not_have_a_start_time_without_a_start_block() {
+        let entry = PlaEntry {
+            id: 126,
+            description: "No Operation".to_string(),
+            line_number: 0,
+            notes: vec![],
+            blocks: vec![]
+        };
+
+        assert!(entry.get_start_time().is_none());
+        assert!(entry.get_end_time().is_none());
+    }
+
+    #[test]
+    fn it_should_get_the_color_of_an_entry() {
+        let entry = PlaEntry {
+            id: 89,
+            description: "Brewing Process".to_string(),
+            line_number: 0,
+            notes: vec![],
+            blocks: vec![
+                PlaSubBlock::from(PlaColorBlock::try_from((89, "color Gold")).unwrap()),
+            ]
+        };
+
+        assert_eq!(Some(String::from("gold")), entry.get_color());
+    }
+
+    #[test]
+    fn it_should_write_an_entry_as_pla_text() {
+        let entry = PlaEntry {
+            id: 89,
+            description: "Brewing Process".to_string(),
+            line_number: 0,
+            notes: vec![],
+            blocks: vec![
+                PlaSubBlock::from(PlaDurationBlock::try_from((89, "duration 6")).unwrap()),
+                PlaSubBlock::from(PlaStartBlock::try_from((89, "start 2020-10-20 16")).unwrap()),
+            ]
+        };
+
+        assert_eq!("[89] Brewing Process\n    duration 6\n    start 2020-10-20 16\n", entry.to_string());
+    }
+
+    #[test]
+    fn it_should_give_typed_access_to_each_kind_of_block() {
+        let entry = PlaEntry {
+            id: 89,
+            description: "Brewing Process".to_string(),
+            line_number: 0,
+            notes: vec![],
+            blocks: vec![
+                PlaSubBlock::from(PlaDurationBlock::try_from((89, "duration 6")).unwrap()),
+                PlaSubBlock::from(PlaResourceBlock::try_from((89, "res Mash Tun")).unwrap()),
+                PlaSubBlock::from(PlaDependencyBlock::try_from((89, "dep 77")).unwrap()),
+                PlaSubBlock::from(PlaResourceBlock::try_from((89, "res Kettle")).unwrap()),
+                PlaSubBlock::from(PlaChildBlock::try_from((89, "child 122")).unwrap()),
+            ]
+        };
+
+        assert!(entry.start().is_none());
+        assert_eq!(Duration::hours(6), entry.duration().unwrap().duration);
+        assert_eq!(vec!["Mash Tun", "Kettle"], entry.resources().iter().map(|r| r.resource_name.as_str()).collect::<Vec<&str>>());
+        assert_eq!(77, entry.dependencies()[0].dependency_id);
+        assert_eq!(122, entry.children()[0].child_id);
+
+        let resources = entry.blocks.iter().filter(|b| matches!(b, PlaSubBlock::Resource(_))).count();
+        assert_eq!(2, resources);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn it_should_serialize_an_entry_and_its_blocks() {
+        let entry = PlaEntry {
+            id: 89,
+            description: "Brewing Process".to_string(),
+            line_number: 0,
+            notes: vec![],
+            blocks: vec![
+                PlaSubBlock::from(PlaDurationBlock::try_from((89, "duration 90m")).unwrap()),
+                PlaSubBlock::from(PlaStartBlock::try_from((89, "start 2020-10-20 16 +02:00")).unwrap()),
+            ]
+        };
+
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#"{"duration":{"parent_id":89,"line_number":0,"duration":90}}"#));
+        assert!(json.contains(r#""time":"2020-10-20T16:00:00","zone":"+02:00""#));
+
+        let deserialized: PlaEntry = serde_json::from_str(&json).unwrap();
+        assert_eq!(entry.to_string(), deserialized.to_string());
     }
 }
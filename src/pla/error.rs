@@ -1,59 +1,218 @@
-use std::fmt;
+use std::{fmt, io};
+use std::error::Error;
 use std::fmt::{Display, Formatter};
-use crate::pla::command::PlaCommand;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+/// Text that couldn't be parsed. Line and column are 1-based, and are 0 when the text wasn't read
+/// from a file, e.g. when a single block is parsed from a string.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
 pub struct PlaParseError {
     pub message: String,
+    pub line_number: usize,
+    pub column: usize,
+
+    /// The offending text, or empty if it isn't known.
+    pub text: String,
+
+    /// The whole line the error occurred on, as it appears in the file.
+    pub source_line: String,
+
+    /// Name of the file the error occurred in, if it was read from one.
+    pub file: Option<String>,
+}
+
+impl PlaParseError {
+    pub fn new(message: &str) -> PlaParseError {
+        PlaParseError {
+            message: String::from(message),
+            ..Default::default()
+        }
+    }
+
+    /// The same error, attributed to `text` found at `column` of `line_number`.
+    pub fn at(self, line_number: usize, column: usize, text: &str) -> PlaParseError {
+        PlaParseError {
+            line_number,
+            column,
+            text: String::from(text),
+            ..self
+        }
+    }
+
+    pub fn with_source_line(self, source_line: &str) -> PlaParseError {
+        PlaParseError {
+            source_line: String::from(source_line),
+            ..self
+        }
+    }
+
+    pub fn with_file(self, file: &str) -> PlaParseError {
+        PlaParseError {
+            file: Some(String::from(file)),
+            ..self
+        }
+    }
 }
 
 impl Display for PlaParseError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "PlaParseError: {}", self.message)
+        if self.line_number == 0 {
+            return write!(f, "PlaParseError: {}", self.message);
+        }
+
+        write!(f, "PlaParseError: line {}, column {}: {}: `{}`", self.line_number, self.column, self.message, self.text)
+    }
+}
+
+impl Error for PlaParseError {}
+
+/// Errors encountered while reading and parsing a pla file.
+#[derive(Debug)]
+pub enum PlaError {
+    /// The file couldn't be read.
+    Io(io::Error),
+
+    /// A line isn't valid pla, e.g. a `duration` without a number.
+    Syntax(PlaParseError),
+
+    /// A line is valid pla but doesn't make sense where it appears, e.g. a block before the
+    /// first entry or an entry id that was already used.
+    Semantic(PlaParseError),
+}
+
+impl Display for PlaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaError::Io(e) => write!(f, "Unable to read pla file: {}", e),
+            PlaError::Syntax(e) | PlaError::Semantic(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl PlaError {
+    /// The same error, with any syntax or semantic error attributed to `file`.
+    pub fn with_file(self, file: &str) -> PlaError {
+        match self {
+            PlaError::Io(e) => PlaError::Io(e),
+            PlaError::Syntax(e) => PlaError::Syntax(e.with_file(file)),
+            PlaError::Semantic(e) => PlaError::Semantic(e.with_file(file)),
+        }
+    }
+}
+
+impl Error for PlaError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            PlaError::Io(e) => Some(e),
+            PlaError::Syntax(e) | PlaError::Semantic(e) => Some(e),
+        }
+    }
+}
+
+impl From<io::Error> for PlaError {
+    fn from(e: io::Error) -> Self {
+        PlaError::Io(e)
+    }
+}
+
+impl From<PlaParseError> for PlaError {
+    fn from(e: PlaParseError) -> Self {
+        PlaError::Syntax(e)
+    }
+}
+
+/// Errors encountered while resolving `child` blocks into a tree of entries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaTreeError {
+    /// A `child` block refers to an id that has no matching entry.
+    MissingChild { parent_id: u32, child_id: u32 },
+
+    /// Following `child` blocks leads back to an entry already on the path. The path starts and
+    /// ends with the same id.
+    Cycle { path: Vec<u32> },
+}
+
+impl Display for PlaTreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            PlaTreeError::MissingChild { parent_id, child_id } => {
+                write!(f, "Entry {} lists child {}, which does not exist", parent_id, child_id)
+            },
+            PlaTreeError::Cycle { path } => {
+                let path_str: Vec<String> = path.iter().map(|id| id.to_string()).collect();
+                write!(f, "Cycle detected in child entries: {}", path_str.join(" -> "))
+            },
+        }
     }
 }
 
-#[derive(Clone, Debug, PartialEq)]
-pub struct PlaSubBlockConversionError {
-    pub initial_type: PlaCommand
+/// Errors encountered while computing the effective start and end of every entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaScheduleError {
+    /// The `child` blocks of the file could not be resolved into a hierarchy.
+    Hierarchy(PlaTreeError),
+
+    /// An entry's start depends, through `dep` and `child` blocks, on its own end. The path
+    /// starts and ends with the same id.
+    DependencyCycle { path: Vec<u32> },
+
+    /// An entry would start or end outside the times that can be represented.
+    OutOfRange { id: u32 },
 }
 
-impl Display for PlaSubBlockConversionError {
+impl Display for PlaScheduleError {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
-        write!(f, "Unable to convert from a PlaSubBlock to the requested concrete type: {:?}", self.initial_type)
+        match self {
+            PlaScheduleError::Hierarchy(e) => write!(f, "Unable to schedule entries: {}", e),
+            PlaScheduleError::DependencyCycle { path } => {
+                let path_str: Vec<String> = path.iter().map(|id| id.to_string()).collect();
+                write!(f, "Cycle detected in entry dependencies: {}", path_str.join(" -> "))
+            },
+            PlaScheduleError::OutOfRange { id } => write!(f, "Entry {} starts or ends outside the dates that can be scheduled", id),
+        }
+    }
+}
+
+impl From<PlaTreeError> for PlaScheduleError {
+    fn from(e: PlaTreeError) -> Self {
+        PlaScheduleError::Hierarchy(e)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::pla::command::PlaCommand;
-    use crate::pla::error::{PlaSubBlockConversionError, PlaParseError};
+    use std::io;
+    use crate::pla::error::{PlaError, PlaParseError, PlaScheduleError, PlaTreeError};
 
     fn get_error() -> Result<u32, PlaParseError> {
         Err(
-            PlaParseError {
-                message: String::from("I've got a caribbean soul I can barely control")
-            }
-        )
-    }
-
-    fn get_conversion_error() -> Result<u32, PlaSubBlockConversionError> {
-        Err(
-            PlaSubBlockConversionError {
-                initial_type: PlaCommand::DURATION
-            }
+            PlaParseError::new("I've got a caribbean soul I can barely control")
         )
     }
 
     #[test]
     fn it_should_be_able_to_create_a_pla_parse_error() {
-        let error: PlaParseError = PlaParseError {
-            message: String::from("I've got a caribbean soul I can barely control")
-        };
+        let error: PlaParseError = PlaParseError::new("I've got a caribbean soul I can barely control");
 
         assert_eq!(format!("PlaParseError: {}", error.message), format!("{}", error))
     }
 
+    #[test]
+    fn it_should_display_where_a_pla_parse_error_occurred() {
+        let error = PlaParseError::new("Cannot parse duration for duration command as u32").at(7, 5, "duration six");
+
+        assert_eq!("PlaParseError: line 7, column 5: Cannot parse duration for duration command as u32: `duration six`",
+                   format!("{}", error));
+    }
+
+    #[test]
+    fn it_should_display_a_pla_error() {
+        let io_error = PlaError::from(io::Error::new(io::ErrorKind::NotFound, "No such file or directory"));
+        assert_eq!("Unable to read pla file: No such file or directory", format!("{}", io_error));
+
+        let syntax_error = PlaError::from(PlaParseError::new("invalid entry id").at(1, 2, "abc"));
+        assert_eq!("PlaParseError: line 1, column 2: invalid entry id: `abc`", format!("{}", syntax_error));
+    }
+
     #[test]
     #[should_panic]
     fn it_should_be_able_to_throw_a_pla_parse_error() {
@@ -62,18 +221,20 @@ mod tests {
     }
 
     #[test]
-    fn it_should_be_able_to_create_a_pla_sub_block_conversion_error() {
-        let error: PlaSubBlockConversionError = PlaSubBlockConversionError {
-            initial_type: PlaCommand::DURATION
-        };
+    fn it_should_display_a_pla_tree_error() {
+        let missing = PlaTreeError::MissingChild { parent_id: 10000, child_id: 89 };
+        assert_eq!("Entry 10000 lists child 89, which does not exist", format!("{}", missing));
 
-        assert_eq!(format!("Unable to convert from a PlaSubBlock to the requested concrete type: DURATION"), format!("{}", error));
+        let cycle = PlaTreeError::Cycle { path: vec![1, 2, 1] };
+        assert_eq!("Cycle detected in child entries: 1 -> 2 -> 1", format!("{}", cycle));
     }
 
     #[test]
-    #[should_panic]
-    fn it_should_be_able_to_throw_a_pla_sub_block_conversion_error() {
-        let res = get_conversion_error();
-        res.unwrap();
+    fn it_should_display_a_pla_schedule_error() {
+        let cycle = PlaScheduleError::DependencyCycle { path: vec![89, 90, 89] };
+        assert_eq!("Cycle detected in entry dependencies: 89 -> 90 -> 89", format!("{}", cycle));
+
+        let out_of_range = PlaScheduleError::OutOfRange { id: 89 };
+        assert_eq!("Entry 89 starts or ends outside the dates that can be scheduled", format!("{}", out_of_range));
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,33 @@
+use crate::pla::entry::PlaEntry;
+use crate::pla::sub_blocks::PlaSubBlock;
+
+/// An entry with the given blocks, each written as it would be in a pla file.
+pub fn get_entry(id: u32, description: &str, blocks: &[&str]) -> PlaEntry {
+    PlaEntry {
+        id,
+        description: String::from(description),
+        line_number: 0,
+        notes: vec![],
+        blocks: blocks
+            .iter()
+            .map(|b| PlaSubBlock::try_from((id, *b)).unwrap())
+            .collect()
+    }
+}
+
+pub fn get_task(id: u32, description: &str, start: &str, duration: &str) -> PlaEntry {
+    get_entry(id, description, &[start, duration])
+}
+
+pub fn get_parent(id: u32, description: &str, child_ids: Vec<u32>) -> PlaEntry {
+    let blocks: Vec<String> = child_ids.iter().map(|c| format!("child {}", c)).collect();
+    let blocks: Vec<&str> = blocks.iter().map(String::as_str).collect();
+    get_entry(id, description, &blocks)
+}
+
+pub fn get_dependent_task(id: u32, description: &str, duration: &str, dependency_ids: Vec<u32>) -> PlaEntry {
+    let mut blocks = vec![String::from(duration)];
+    blocks.extend(dependency_ids.iter().map(|d| format!("dep {}", d)));
+    let blocks: Vec<&str> = blocks.iter().map(String::as_str).collect();
+    get_entry(id, description, &blocks)
+}
@@ -0,0 +1,148 @@
+/// 1-based line and column of a character within pla text. Line 0 is text that wasn't read from
+/// a file, e.g. a single block parsed from a string.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct PlaPosition {
+    pub line_number: usize,
+    pub column: usize,
+}
+
+/// The text from `start` up to, but not including, `end`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PlaSpan {
+    pub start: PlaPosition,
+    pub end: PlaPosition,
+}
+
+impl PlaSpan {
+    /// The `length` characters from `column` of `line_number`.
+    pub fn new(line_number: usize, column: usize, length: usize) -> PlaSpan {
+        PlaSpan {
+            start: PlaPosition { line_number, column },
+            end: PlaPosition { line_number, column: column + length },
+        }
+    }
+
+    /// The span from the start of this one to the end of `other`.
+    pub fn to(self, other: PlaSpan) -> PlaSpan {
+        PlaSpan { start: self.start, end: other.end }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaTokenKind {
+    OpenBracket,
+    CloseBracket,
+    Word,
+    Comment,
+}
+
+/// A piece of a line of pla text. The text of a comment is what follows its `#` or `//`, without
+/// surrounding whitespace.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaToken {
+    pub kind: PlaTokenKind,
+    pub text: String,
+    pub span: PlaSpan,
+}
+
+/// Split a line of pla text into tokens. Any run of whitespace, including tabs, separates tokens,
+/// and brackets are tokens of their own. A comment starts with `#` or `//` at the start of a token
+/// and runs to the end of the line. A `#` after the first token must also be followed by
+/// whitespace or end the line, so that colours like `#a0c8ff` aren't comments.
+pub fn tokenize(line: &str, line_number: usize) -> Vec<PlaToken> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut tokens: Vec<PlaToken> = vec![];
+    let mut idx = 0;
+
+    while idx < chars.len() {
+        let c = chars[idx];
+        if c.is_whitespace() {
+            idx += 1;
+            continue;
+        }
+
+        let next = chars.get(idx + 1).copied();
+        let marker_len = if c == '/' && next == Some('/') {
+            2
+        } else if c == '#' && (tokens.is_empty() || next.map_or(true, char::is_whitespace)) {
+            1
+        } else {
+            0
+        };
+
+        let start = idx;
+        let kind = if marker_len > 0 {
+            idx = chars.len();
+            PlaTokenKind::Comment
+        } else if c == '[' {
+            idx += 1;
+            PlaTokenKind::OpenBracket
+        } else if c == ']' {
+            idx += 1;
+            PlaTokenKind::CloseBracket
+        } else {
+            while idx < chars.len() && !chars[idx].is_whitespace() && chars[idx] != '[' && chars[idx] != ']' {
+                idx += 1;
+            }
+            PlaTokenKind::Word
+        };
+
+        let text: String = chars[start + marker_len..idx].iter().collect();
+        tokens.push(PlaToken {
+            kind,
+            text: String::from(text.trim()),
+            span: PlaSpan::new(line_number, start + 1, idx - start),
+        });
+    }
+
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pla::lexer::{tokenize, PlaSpan, PlaTokenKind};
+
+    #[test]
+    fn it_should_split_a_line_on_any_whitespace() {
+        let tokens = tokenize("\tstart  2021-01-15\t15", 4);
+        let texts: Vec<&str> = tokens.iter().map(|t| t.text.as_str()).collect();
+        assert_eq!(vec!["start", "2021-01-15", "15"], texts);
+        assert!(tokens.iter().all(|t| t.kind == PlaTokenKind::Word));
+        assert_eq!(PlaSpan::new(4, 9, 10), tokens[1].span);
+        assert_eq!(PlaSpan::new(4, 20, 2), tokens[2].span);
+    }
+
+    #[test]
+    fn it_should_tokenize_an_entry_header_with_a_comment() {
+        let tokens = tokenize("[89]Brewing [Mash] # first batch", 1);
+        let kinds: Vec<PlaTokenKind> = tokens.iter().map(|t| t.kind).collect();
+        assert_eq!(vec![
+            PlaTokenKind::OpenBracket, PlaTokenKind::Word, PlaTokenKind::CloseBracket, PlaTokenKind::Word,
+            PlaTokenKind::OpenBracket, PlaTokenKind::Word, PlaTokenKind::CloseBracket, PlaTokenKind::Comment,
+        ], kinds);
+        assert_eq!("first batch", tokens[7].text);
+        assert_eq!(PlaSpan::new(1, 20, 13), tokens[7].span);
+    }
+
+    #[test]
+    fn it_should_only_start_comments_at_the_start_of_a_token() {
+        let texts = |line: &str| -> Vec<(PlaTokenKind, String)> {
+            tokenize(line, 1).into_iter().map(|t| (t.kind, t.text)).collect()
+        };
+
+        assert_eq!(vec![(PlaTokenKind::Comment, String::from("res Keg 1"))], texts("    #res Keg 1"));
+        assert_eq!(2, texts("color #a0c8ff").len());
+        assert_eq!(2, texts("res http://example.com").len());
+        assert_eq!(PlaTokenKind::Comment, texts("duration 6 // could be 7")[2].0);
+        assert_eq!(PlaTokenKind::Comment, texts("duration 6 //7")[2].0);
+    }
+
+    #[test]
+    fn it_should_accept_a_bare_hash_at_the_end_of_a_line() {
+        let tokens = tokenize("duration 6 #", 1);
+        assert_eq!(3, tokens.len());
+        assert_eq!(PlaTokenKind::Comment, tokens[2].kind);
+        assert_eq!("", tokens[2].text);
+        assert_eq!(PlaSpan::new(1, 12, 1), tokens[2].span);
+    }
+}
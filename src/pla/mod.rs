@@ -1,5 +1,17 @@
 pub mod sub_blocks;
 pub mod command;
 pub mod error;
+pub mod diagnostic;
 pub mod parser;
 pub mod entry;
+pub mod tree;
+pub mod schedule;
+pub mod validate;
+pub mod resources;
+pub mod time_zone;
+pub mod lexer;
+pub mod syntax;
+#[cfg(feature = "serde")]
+pub mod document;
+#[cfg(test)]
+pub mod fixtures;
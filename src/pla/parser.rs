@@ -1,308 +1,491 @@
 use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
 use std::fs::File;
-use std::io::{Error, Read};
+use std::io::Read;
 use std::path::Path;
 use std::str::FromStr;
-use regex::Regex;
-use crate::{box_from_upcast, push_entry_sub_block};
 use crate::pla::command::PlaCommand;
-use crate::pla::entry::PlaEntry;
-use crate::pla::sub_blocks::{PlaChildBlock, PlaDependencyBlock, PlaDurationBlock, PlaResourceBlock, PlaStartBlock, PlaSubBlock};
+use crate::pla::diagnostic::{PlaDiagnostic, PlaSeverity};
+#[cfg(feature = "serde")]
+use crate::pla::document::PlaDocument;
+use crate::pla::entry::{PlaEntry, PlaNote};
+use crate::pla::error::{PlaError, PlaParseError, PlaScheduleError, PlaTreeError};
+use crate::pla::sub_blocks::PlaSubBlock;
+use crate::pla::resources::{find_conflicts, PlaResourceConflict};
+use crate::pla::schedule::PlaSchedule;
+use crate::pla::syntax;
+use crate::pla::syntax::{PlaBlockNode, PlaCommentNode, PlaHeaderNode, PlaItemNode};
+use crate::pla::time_zone::PlaTimeZone;
+use crate::pla::tree::PlaTree;
+use crate::pla::validate::{validate, PlaViolation};
 
 pub struct PlaParser {
     pub entries: Vec<PlaEntry>,
 
     // mapping of ids to the index in the vector above
     id_map: Option<HashMap<u32, usize>>,
+
+    // lines of the parsed text, and the file they were read from, for diagnostics
+    source: Vec<String>,
+    file: Option<String>,
+
+    // lines that were skipped because they weren't understood, rather than because they're wrong
+    warnings: Vec<PlaParseError>,
 }
 
 impl PlaParser {
-    pub fn new(file_path: &Path) -> Result<PlaParser, Error> {
-        let display = file_path.display();
+    pub fn new(file_path: &Path) -> Result<PlaParser, PlaError> {
+        let lines = PlaParser::read_lines(File::open(file_path)?)?;
+        let file = format!("{}", file_path.display());
+        let mut pla_parser = PlaParser::parse(lines).map_err(|e| e.with_file(&file))?;
+        pla_parser.file = Some(file);
 
-        // Open file for reading
-        let mut file = match File::open(file_path) {
-            Err(why) => panic!("Couldn't open {}: {}", display, why),
-            Ok(f) => f
-        };
+        Ok(pla_parser)
+    }
 
-        // Read all lines and parse the file
-        let mut contents: String = String::from("");
-        match file.read_to_string(&mut contents) {
-            Ok(size) => size,
-            Err(e) => panic!("Unable to parse file from contents: {}", e.to_string()),
-        };
+    /// Parse pla text from `reader`, such as standard input.
+    pub fn from_reader(reader: impl Read) -> Result<PlaParser, PlaError> {
+        PlaParser::parse(PlaParser::read_lines(reader)?)
+    }
 
-        // Read the file into a vec, skipping blank lines
-        let lines = contents.split("\n").map(|s| String::from(s)).filter(|l| !l.is_empty()).collect();
+    /// Parse a file in which every line must be understood. Unknown commands, which
+    /// [`PlaParser::new`] skips with a warning, are errors.
+    pub fn new_strict(file_path: &Path) -> Result<PlaParser, PlaError> {
+        PlaParser::new(file_path)?.strict()
+    }
+
+    /// Parse as much of a file as possible. Blocks that can't be parsed are skipped, as are
+    /// entries whose header can't be, along with their blocks. Returns the entries that remain
+    /// and a diagnostic for every line that was skipped, failing only if the file can't be read.
+    pub fn new_lenient(file_path: &Path) -> Result<(PlaParser, Vec<PlaDiagnostic>), PlaError> {
+        let lines = PlaParser::read_lines(File::open(file_path)?)?;
 
-        PlaParser::parse(lines)
+        Ok(PlaParser::lenient(lines, Some(format!("{}", file_path.display()))))
     }
 
-    pub fn get_entry_by_id(&self, id: u32) -> Option<PlaEntry> {
-        match &self.id_map {
-            Some(x) => {
-                let borrowed_entry = x.get(&id);
-                let retval = match borrowed_entry {
-                    Some(be) => Some(self.entries[*be].clone()),
+    /// Parse as much pla text as possible, as [`PlaParser::new_lenient`] does for a file.
+    pub fn from_str_lenient(text: &str) -> (PlaParser, Vec<PlaDiagnostic>) {
+        PlaParser::lenient(PlaParser::split_lines(text), None)
+    }
 
-                    None => None,
-                };
+    /// Parse as much pla text from `reader` as possible, as [`PlaParser::new_lenient`] does for a
+    /// file.
+    pub fn from_reader_lenient(reader: impl Read) -> Result<(PlaParser, Vec<PlaDiagnostic>), PlaError> {
+        Ok(PlaParser::lenient(PlaParser::read_lines(reader)?, None))
+    }
 
-                retval
-            }
-            None => None
+    /// The entries of a schedule document, such as one read with [`PlaDocument::from_json`].
+    /// They're checked as entries in a pla file are, failing on the first problem found.
+    #[cfg(feature = "serde")]
+    pub fn from_document(document: &PlaDocument) -> Result<PlaParser, PlaError> {
+        let (pla_parser, errors) = PlaParser::document(document);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(pla_parser),
         }
     }
 
-    fn create_hierarchy(lines: &Vec<PlaLine>) -> Vec<HeirarchicalPlaLine> {
-        let mut id: Option<u32> = None;
+    /// As many entries of a schedule document as possible, as [`PlaParser::new_lenient`] reads a
+    /// file, with a diagnostic for every entry or value that was skipped.
+    #[cfg(feature = "serde")]
+    pub fn from_document_lenient(document: &PlaDocument) -> (PlaParser, Vec<PlaDiagnostic>) {
+        let (pla_parser, errors) = PlaParser::document(document);
+
+        (pla_parser, errors.iter().map(PlaDiagnostic::from).collect())
+    }
+
+    #[cfg(feature = "serde")]
+    fn document(document: &PlaDocument) -> (PlaParser, Vec<PlaError>) {
+        let (entries, errors) = document.to_entries();
+        let map = PlaParser::build_map(&entries);
+        let pla_parser = PlaParser {
+            entries,
+            id_map: Some(map),
+            source: vec![],
+            file: None,
+            warnings: vec![],
+        };
+
+        (pla_parser, errors)
+    }
 
-        lines
+    fn lenient(lines: Vec<String>, file: Option<String>) -> (PlaParser, Vec<PlaDiagnostic>) {
+        let (mut pla_parser, errors) = PlaParser::parse_lenient(lines);
+        let mut diagnostics: Vec<PlaDiagnostic> = errors
             .into_iter()
-            .map(|l| {
-                let parent_id = match l.command {
-                    PlaCommand::ENTRY => {
-                        id = l.get_id();
-                        None
-                    }                   ,
-                    _ => id,
-                };
+            .map(|e| match &file {
+                Some(file) => e.with_file(file),
+                None => e,
+            })
+            .map(|e| PlaDiagnostic::from(&e))
+            .collect();
+
+        pla_parser.file = file;
+        diagnostics.extend(pla_parser.get_warnings());
+        diagnostics.sort_by_key(|d| d.line_number);
 
-                HeirarchicalPlaLine {
-                    command: l.command.clone(),
-                    text: String::from(&l.text),
-                    parent_id,
+        (pla_parser, diagnostics)
+    }
+
+    fn read_lines(mut reader: impl Read) -> Result<Vec<String>, PlaError> {
+        let mut contents: String = String::from("");
+        reader.read_to_string(&mut contents)?;
+
+        Ok(PlaParser::split_lines(&contents))
+    }
+
+    /// Lines of pla text. Blank lines are kept so that line numbers match the text.
+    fn split_lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    /// Lines that were skipped because they weren't understood, such as unknown commands.
+    pub fn get_warnings(&self) -> Vec<PlaDiagnostic> {
+        self.warnings
+            .iter()
+            .map(|w| {
+                let diagnostic = PlaDiagnostic { severity: PlaSeverity::Warning, ..PlaDiagnostic::from(w) };
+                match &self.file {
+                    Some(file) => diagnostic.with_file(file),
+                    None => diagnostic,
                 }
             })
-            .filter(|hl| {
-                hl.command != PlaCommand::UNKNOWN
-            })
             .collect()
     }
 
-    fn parse_pla_lines(lines: Vec<String>) -> Vec<PlaLine> {
-        let pla_lines: Vec<PlaLine> = lines
-            .into_iter()
-            .map(|l| PlaLine::parse_line(l))
-            .filter(|potential_line| potential_line.is_some())
-            .map(|l| l.unwrap())
-            .collect();
+    /// This parser, or an error for the first of its warnings, for callers that require every
+    /// line to be understood.
+    pub fn strict(self) -> Result<PlaParser, PlaError> {
+        match self.warnings.first() {
+            Some(w) => {
+                let error = match &self.file {
+                    Some(file) => w.clone().with_file(file),
+                    None => w.clone(),
+                };
+                Err(PlaError::Syntax(error))
+            },
+            None => Ok(self),
+        }
+    }
 
-        pla_lines
+    pub fn get_entry_by_id(&self, id: u32) -> Option<PlaEntry> {
+        match &self.id_map {
+            Some(x) => x.get(&id).map(|be| self.entries[*be].clone()),
+            None => None
+        }
     }
 
-    fn parse(lines: Vec<String>) -> Result<PlaParser, Error> {
-        // The file format for pla is available here:
-        // https://www.arpalert.org/pla.html
-        let pla_lines = PlaParser::parse_pla_lines(lines);
-        let heirarchy: Vec<HeirarchicalPlaLine> = PlaParser::create_hierarchy(&pla_lines);
+    /// Arrange the parsed entries into a tree by resolving their `child` blocks.
+    pub fn build_tree(&self) -> Result<PlaTree, PlaTreeError> {
+        PlaTree::new(&self.entries)
+    }
 
-        let entries: Vec<PlaEntry> = pla_lines
-            .into_iter()
-            .filter(|line| line.command == PlaCommand::ENTRY)
-            .map(|line| {
-                let entry = match line.command {
-                    PlaCommand::ENTRY => {
-                        let entry_regex = Regex::new(r"^\[(\d*)\](\s)*(.*)").unwrap();
-                        let id_capture = entry_regex.captures(&line.text).unwrap().get(1).map_or(String::from(""), |m| String::from(m.as_str()));
-                        PlaEntry {
-                            id: id_capture.parse::<u32>().unwrap(),
-                            description: entry_regex.captures(&line.text).unwrap().get(3).map_or(String::from(""), |m| String::from(m.as_str())),
-                            children: None
-                        }
-                    },
-                    _ => PlaEntry {
-                        id: 0,
-                        description: String::from(""),
-                        children: None
-                    }
-                };
-                entry
-            }).collect();
+    /// Compute the effective start and end of every entry, deriving parents from their children.
+    pub fn schedule(&self) -> Result<PlaSchedule, PlaScheduleError> {
+        PlaSchedule::new(&self.entries)
+    }
+
+    /// Like `schedule`, but with times as clocks in `zone` read them.
+    pub fn schedule_in(&self, zone: &PlaTimeZone) -> Result<PlaSchedule, PlaScheduleError> {
+        PlaSchedule::in_time_zone(&self.entries, zone)
+    }
 
-        let sub_blocks: Vec<Box<dyn PlaSubBlock>> = heirarchy
+    /// Check the parsed entries for dangling ids, cycles and entries that start before their
+    /// dependencies end.
+    pub fn validate(&self) -> Vec<PlaViolation> {
+        validate(&self.entries)
+    }
+
+    /// Find every pair of entries that book the same resource at overlapping times.
+    pub fn find_resource_conflicts(&self) -> Result<Vec<PlaResourceConflict>, PlaScheduleError> {
+        let schedule = self.schedule()?;
+        Ok(find_conflicts(&self.entries, &schedule))
+    }
+
+    /// Validate the parsed entries and look for double-booked resources, pointing each problem at
+    /// the line of the block that caused it.
+    pub fn check(&self) -> Vec<PlaDiagnostic> {
+        let violations = self.validate()
             .into_iter()
-            .filter(|x|
-                x.command == PlaCommand::START
-                    || x.command == PlaCommand::CHILD
-                    || x.command == PlaCommand::DURATION
-                    || x.command == PlaCommand::DEPENDENCY
-                    || x.command == PlaCommand::RESOURCE)
-            .map(|hl| {
-                match hl.command {
-                    PlaCommand::START => box_from_upcast!{PlaStartBlock, hl},
-                    PlaCommand::CHILD => box_from_upcast!{PlaChildBlock, hl},
-                    PlaCommand::DURATION => box_from_upcast!{PlaDurationBlock, hl},
-                    PlaCommand::RESOURCE => box_from_upcast!{PlaResourceBlock, hl},
-                    PlaCommand::DEPENDENCY => box_from_upcast!{PlaDependencyBlock, hl},
-                    _ => panic!("Unable to parse sub block with command: {:?}", hl.command)
-                }
-            })
-            .collect();
+            .map(|v| self.diagnose(&v.get_message(), v.get_line_number()));
 
-        // Now, post-process all entries so that the appropriate children are included
-        let hierarchical_entries: Vec<PlaEntry> = entries
+        // Resource conflicts can only be found once every entry can be scheduled, which the
+        // violations above will already have explained if it isn't possible.
+        let conflicts = self.find_resource_conflicts()
+            .unwrap_or_default()
             .into_iter()
-            .map(|e| {
-                let mut entry_sb: Vec<Box<dyn PlaSubBlock>> = vec![];
-                sub_blocks
-                    .iter()
-                    .filter(|sb| {
-                        sb.get_parent_id() == e.id
-                    })
-                    .filter(|sb| {
-                        sb.get_command() == PlaCommand::START
-                        || sb.get_command() == PlaCommand::CHILD
-                        || sb.get_command() == PlaCommand::DURATION
-                        || sb.get_command() == PlaCommand::DEPENDENCY
-                        || sb.get_command() == PlaCommand::RESOURCE
-                    })
-                    .for_each (|sb| {
-                        match sb.get_command() {
-                            PlaCommand::START => {
-                                push_entry_sub_block!{PlaStartBlock, sb, entry_sb}
-                            },
-                            PlaCommand::CHILD => {
-                                push_entry_sub_block!{PlaChildBlock, sb, entry_sb}
-                            },
-                            PlaCommand::DURATION => {
-                                push_entry_sub_block!{PlaDurationBlock, sb, entry_sb}
-                            },
-                            PlaCommand::DEPENDENCY => {
-                                push_entry_sub_block!{PlaDependencyBlock, sb, entry_sb}
-                            },
-                            PlaCommand::RESOURCE => {
-                                push_entry_sub_block!{PlaResourceBlock, sb, entry_sb}
-                            },
-                            _ => {}
-                        }
-
-                    });
-                (e, entry_sb)
-            })
-            .map(|(e, mut entry_sb)| {
-                let sub_block_children: Vec<Box<dyn PlaSubBlock>> = entry_sb.drain(0..).collect();
-                let mut children_retval: Option<Vec<Box<dyn PlaSubBlock>>> = None;
-                if sub_block_children.len() > 0 {
-                    children_retval = Some(sub_block_children);
-                }
-                PlaEntry {
-                    description: String::from(&e.description),
-                    id: e.id,
-                    children: children_retval
-                }
-            })
-            .collect();
-        for next_hentry in &hierarchical_entries {
-            println!("Hierarchicial entry: {:?}", next_hentry);
+            .map(|c| self.diagnose(&c.get_message(), Some(c.second.line_number)));
+
+        violations.chain(conflicts).collect()
+    }
+
+    /// An error pointing at the whole of the given line, after its indentation.
+    fn diagnose(&self, message: &str, line_number: Option<usize>) -> PlaDiagnostic {
+        let mut diagnostic = PlaDiagnostic::error(message);
+        let source_line = line_number.and_then(|n| Some((n, self.source.get(n.checked_sub(1)?)?)));
+        if let Some((n, source_line)) = source_line {
+            let column = source_line.chars().count() - source_line.trim_start().chars().count() + 1;
+            diagnostic = diagnostic.at(n, column, source_line.trim().chars().count(), source_line);
         }
 
-        let map = PlaParser::build_map(&hierarchical_entries);
-        Ok(PlaParser {
-            entries: hierarchical_entries,
-            id_map: Some(map),
-        })
+        match &self.file {
+            Some(file) => diagnostic.with_file(file),
+            None => diagnostic,
+        }
     }
 
-    fn build_map(entries: &Vec<PlaEntry>) -> HashMap<u32, usize> {
-        let mut map: HashMap<u32, usize> = HashMap::new();
-        let entries_with_id: Vec<PlaEntry> = entries
+    pub(crate) fn parse(lines: Vec<String>) -> Result<PlaParser, PlaError> {
+        let (pla_parser, errors) = PlaParser::parse_lenient(lines);
+        match errors.into_iter().next() {
+            Some(e) => Err(e),
+            None => Ok(pla_parser),
+        }
+    }
+
+    pub(crate) fn parse_lenient(lines: Vec<String>) -> (PlaParser, Vec<PlaError>) {
+        let source = lines.clone();
+        let mut errors: Vec<PlaError> = vec![];
+        let mut warnings: Vec<PlaParseError> = vec![];
+        let entries = PlaParser::parse_entries(lines, &mut errors, &mut warnings);
+        let errors = errors
             .into_iter()
-            .map(|e| PlaEntry {
-                id: e.id.clone(),
-                description: e.description.clone(),
-                children: None
-            })
+            .map(|e| PlaParser::with_source_line(e, &source))
+            .collect();
+        let warnings = warnings
+            .into_iter()
+            .map(|w| PlaParser::attach_source_line(w, &source))
             .collect();
 
-        for next_entry_idx in 0..entries_with_id.len() {
-            let next_entry = &entries_with_id[next_entry_idx];
-            map.insert(next_entry.id, next_entry_idx);
+        let map = PlaParser::build_map(&entries);
+        let pla_parser = PlaParser {
+            entries,
+            id_map: Some(map),
+            source,
+            file: None,
+            warnings,
+        };
+
+        (pla_parser, errors)
+    }
+
+    fn with_source_line(e: PlaError, source: &[String]) -> PlaError {
+        match e {
+            PlaError::Io(e) => PlaError::Io(e),
+            PlaError::Syntax(e) => PlaError::Syntax(PlaParser::attach_source_line(e, source)),
+            PlaError::Semantic(e) => PlaError::Semantic(PlaParser::attach_source_line(e, source)),
         }
+    }
 
-        map
+    fn attach_source_line(e: PlaParseError, source: &[String]) -> PlaParseError {
+        match e.line_number.checked_sub(1).and_then(|idx| source.get(idx)) {
+            Some(l) => e.with_source_line(l),
+            None => e,
+        }
     }
-}
 
-/// Textual definition of a line within the Pla file.
-#[derive(Debug)]
-pub struct PlaLine {
-    command: PlaCommand,
-    text: String,
-}
+    /// Parse every entry and its blocks, in the order they appear, recording an error for each
+    /// line that has to be skipped and a warning for each line that isn't understood.
+    fn parse_entries(lines: Vec<String>, errors: &mut Vec<PlaError>, warnings: &mut Vec<PlaParseError>) -> Vec<PlaEntry> {
+        // The file format for pla is available here:
+        // https://www.arpalert.org/pla.html
+        let (file, syntax_errors) = syntax::parse(&lines);
+        errors.extend(syntax_errors.into_iter().map(PlaError::Syntax));
+
+        let mut entries: Vec<PlaEntry> = vec![];
+
+        // Comments on lines of their own describe the line that follows them, along with any
+        // comment at the end of that line. Indented comments that aren't followed by another
+        // block of the same entry describe the entry instead.
+        let mut pending: Vec<&PlaCommentNode> = vec![];
+
+        for item in &file.preamble {
+            match item {
+                PlaItemNode::Comment(c) => pending.push(c),
+                PlaItemNode::Block(b) => {
+                    pending.clear();
+                    match PlaParser::sub_block_command(b) {
+                        Some(command) => {
+                            let message = format!("`{}` block appears before the first entry", command);
+                            errors.push(PlaError::Semantic(PlaParseError::new(&message)
+                                .at(b.span.start.line_number, b.span.start.column, &b.text)));
+                        },
+                        None => warnings.push(PlaParser::unknown_command(b)),
+                    }
+                },
+            }
+        }
 
-impl PlaLine {
-    fn parse_line(line: String) -> Option<PlaLine> {
-        let mut line = line;
+        // Index of the entry the following blocks belong to. Blocks of an entry that was skipped
+        // are skipped along with it.
+        let mut current: Option<usize> = None;
 
-        // Trim whitespace at the start of the line
-        line = String::from(line.trim_start());
-        line = String::from(line.trim_end());
+        for node in &file.entries {
+            let header = &node.header;
+            let line_number = header.span.start.line_number;
 
-        // If there isn't anything in the line, then just return
-        if line.is_empty() {
-            return None;
-        }
+            let indented: Vec<&PlaCommentNode> = pending.iter().copied().filter(|c| c.span.start.column > 1).collect();
+            pending.retain(|c| c.span.start.column == 1);
+            if let Some(idx) = current {
+                let entry_line_number = entries[idx].line_number;
+                entries[idx].notes.extend(PlaParser::notes(&indented, entry_line_number));
+            }
 
-        // If the line starts with '[', then we have a new entry
-        let command: PlaCommand;
-        let identifier_re = Regex::new(r"^\[(\d*)\](\s)*(.*)").unwrap();
-        if identifier_re.is_match(&line) {
-            command = PlaCommand::ENTRY;
-        } else {
-            let sub_block_re = Regex::new(r"^(\s)*(.+)$").unwrap();
-            if sub_block_re.is_match(&line) {
-                let command_text = sub_block_re.captures(&line)
-                    .unwrap()
-                    .get(2)
-                    .map_or(String::from(""), |m | String::from(m.as_str().trim_start()));
-                let command_split: Vec<&str> = command_text.split(' ').collect();
-                command = PlaCommand::from_str(command_split[0]).unwrap_or(PlaCommand::UNKNOWN);
-            } else {
-                command = PlaCommand::UNKNOWN;
+            current = None;
+            match PlaParser::parse_entry(header) {
+                Ok(entry) => {
+                    if let Some(existing) = entries.iter().find(|e| e.id == entry.id) {
+                        let message = format!("entry {} is already defined on line {}", entry.id, existing.line_number);
+                        let text = match &header.description {
+                            Some(d) => format!("[{}] {}", header.id.text, d.text),
+                            None => format!("[{}]", header.id.text),
+                        };
+                        errors.push(PlaError::Semantic(PlaParseError::new(&message)
+                            .at(line_number, header.span.start.column, &text)));
+                    } else {
+                        pending.extend(&header.comment);
+                        let notes = PlaParser::notes(&pending, line_number);
+                        entries.push(PlaEntry { notes, ..entry });
+                        current = Some(entries.len() - 1);
+                    }
+                },
+                Err(e) => errors.push(PlaError::Syntax(e)),
+            }
+            pending.clear();
+
+            for item in &node.items {
+                let b = match item {
+                    PlaItemNode::Comment(c) => {
+                        pending.push(c);
+                        continue;
+                    },
+                    PlaItemNode::Block(b) => b,
+                };
+
+                if PlaParser::sub_block_command(b).is_none() {
+                    warnings.push(PlaParser::unknown_command(b));
+                    continue;
+                }
+
+                let idx = match current {
+                    Some(idx) => idx,
+                    None => {
+                        pending.clear();
+                        continue;
+                    },
+                };
+
+                let block = PlaSubBlock::try_from((entries[idx].id, b));
+
+                match block {
+                    Ok(block) => {
+                        pending.extend(&b.comment);
+                        let notes = PlaParser::notes(&pending, b.span.start.line_number);
+                        entries[idx].notes.extend(notes);
+                        entries[idx].blocks.push(block);
+                    },
+                    Err(e) => errors.push(PlaError::Syntax(e)),
+                }
+                pending.clear();
             }
         }
 
-        let text = String::from(line);
-        Some(PlaLine {
-            command,
-            text,
+        if let Some(idx) = current {
+            let line_number = entries[idx].line_number;
+            entries[idx].notes.extend(PlaParser::notes(&pending, line_number));
+        }
+
+        // Malformed headers are found before anything else, but are reported in line order.
+        errors.sort_by_key(|e| match e {
+            PlaError::Syntax(e) | PlaError::Semantic(e) => e.line_number,
+            PlaError::Io(_) => 0,
+        });
+
+        entries
+    }
+
+    /// The command of `block`, if it's one that can appear within an entry.
+    fn sub_block_command(block: &PlaBlockNode) -> Option<PlaCommand> {
+        PlaCommand::from_str(&block.command.text)
+            .ok()
+            .filter(|c| PlaCommand::SUB_BLOCK_COMMANDS.contains(c))
+    }
+
+    /// Notes for `comments`, describing the entry or block on `subject_line_number`.
+    fn notes(comments: &[&PlaCommentNode], subject_line_number: usize) -> Vec<PlaNote> {
+        comments
+            .iter()
+            .map(|c| PlaNote {
+                text: String::from(&c.text),
+                line_number: c.span.start.line_number,
+                subject_line_number,
+            })
+            .collect()
+    }
+
+    fn unknown_command(block: &PlaBlockNode) -> PlaParseError {
+        let name = &block.command.text;
+        let message = match PlaCommand::suggest(name) {
+            Some(command) => format!("unknown command `{}`, did you mean `{}`?", name, command),
+            None => format!("unknown command `{}`", name),
+        };
+
+        block.command.error(&message)
+    }
+
+    fn parse_entry(header: &PlaHeaderNode) -> Result<PlaEntry, PlaParseError> {
+        let id = match header.id.text.parse::<u32>() {
+            Ok(id) => id,
+            Err(_) => return Err(header.id.error("unable to parse entry id as u32")),
+        };
+
+        Ok(PlaEntry {
+            id,
+            description: header.description.as_ref().map_or(String::from(""), |d| String::from(&d.text)),
+            line_number: header.span.start.line_number,
+            notes: vec![],
+            blocks: vec![]
         })
     }
 
-    pub fn get_id(&self) -> Option<u32> {
-        let mut id: Option<u32> = None;
-        let identifier_re = Regex::new(r"^\[(\d*)\](\s)*(.*)").unwrap();
-        if identifier_re.is_match(&self.text) {
-            id = identifier_re.captures(&self.text)
-                .unwrap()
-                .get(1)
-                .map_or(None, |m| {
-                    match m.as_str().parse::<u32>() {
-                        Ok(x) => Some(x),
-                        Err(_e) => None,
-                    }
-                });
+    fn build_map(entries: &[PlaEntry]) -> HashMap<u32, usize> {
+        let mut map: HashMap<u32, usize> = HashMap::new();
+        for (next_entry_idx, next_entry) in entries.iter().enumerate() {
+            map.insert(next_entry.id, next_entry_idx);
         }
 
-        id
+        map
+    }
+}
+
+impl FromStr for PlaParser {
+    type Err = PlaError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        PlaParser::parse(PlaParser::split_lines(s))
     }
 }
 
-/// Post-processed, hierarchically ordered line within a Pla file.
-#[derive(Debug)]
-pub struct HeirarchicalPlaLine {
-    pub command: PlaCommand,
-    pub text: String,
-    pub parent_id: Option<u32>,
+/// Displays as a pla file holding every entry, separated by blank lines, that parses back to the
+/// same entries.
+impl Display for PlaParser {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        for (idx, entry) in self.entries.iter().enumerate() {
+            if idx > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{}", entry)?;
+        }
+
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::path::PathBuf;
-    use chrono::NaiveDate;
+    use chrono::{Duration, NaiveDate};
     use super::*;
+    use crate::pla::sub_blocks::PlaStartBlock;
 
     #[test]
     #[should_panic]
@@ -323,9 +506,12 @@ mod tests {
 
         start 2021-01-15 15
         "#;
-        let lines: Vec<String> = text.split("\n").map(|s| String::from(s)).collect();
-        let pla_lines: Vec<PlaLine> = PlaParser::parse_pla_lines(lines);
-        assert_eq!(2, pla_lines.len());
+        let lines: Vec<String> = text.split("\n").map(String::from).collect();
+        let (file, errors) = syntax::parse(&lines);
+        assert!(errors.is_empty());
+        assert_eq!(1, file.entries.len());
+        assert_eq!(5, file.entries[0].header.span.start.line_number);
+        assert_eq!(1, file.entries[0].items.len());
     }
 
     #[test]
@@ -334,13 +520,15 @@ mod tests {
 
         start 2021-01-15 15
         "#;
-        let lines: Vec<String> = text.split("\n").map(|s| String::from(s)).collect();
-        let pla_lines: Vec<PlaLine> = PlaParser::parse_pla_lines(lines);
-        let heir_pla_lines: Vec<HeirarchicalPlaLine> = PlaParser::create_hierarchy(&pla_lines);
-        let pla_start = PlaStartBlock::try_from(&heir_pla_lines[1]);
+        let lines: Vec<String> = text.split("\n").map(String::from).collect();
+        let (file, _) = syntax::parse(&lines);
+        let pla_start = match &file.entries[0].items[0] {
+            PlaItemNode::Block(b) => PlaStartBlock::try_from((10000, b)),
+            item => panic!("Expected a block, got {:?}", item),
+        };
         let unwrapped_ps = pla_start.unwrap();
-        assert_eq!(NaiveDate::from_ymd(2021, 1, 15), unwrapped_ps.date);
-        assert_eq!(15, unwrapped_ps.hour);
+        assert_eq!(NaiveDate::from_ymd(2021, 1, 15).and_hms(15, 0, 0), unwrapped_ps.time);
+        assert_eq!(None, unwrapped_ps.zone);
     }
 
     #[test]
@@ -359,8 +547,8 @@ mod tests {
 
         println!("{:?}", pla_parser.get_entry_by_id(10000).unwrap());
         println!("{:?}", pla_parser.get_entry_by_id(122).unwrap());
-        assert!(pla_parser.get_entry_by_id(10000).unwrap().has_children());
-        assert!(pla_parser.get_entry_by_id(122).unwrap().has_children());
+        assert!(pla_parser.get_entry_by_id(10000).unwrap().has_blocks());
+        assert!(pla_parser.get_entry_by_id(122).unwrap().has_blocks());
     }
 
     #[test]
@@ -380,7 +568,9 @@ mod tests {
         assert_eq!(PlaEntry {
             id: 10000,
             description: String::from("Autumn's Early Arrival Blonde (Batch: 10000)"),
-            children: None
+            line_number: 3,
+            notes: vec![],
+            blocks: vec![]
         }, pla_parser.get_entry_by_id(10000).unwrap());
 
         println!("{:?}", pla_parser.get_entry_by_id(10000).unwrap());
@@ -388,4 +578,284 @@ mod tests {
         // We shouldn't be able to get a nonexistent id
         assert_eq!(None, pla_parser.get_entry_by_id(2018271));
     }
-}
\ No newline at end of file
+
+    fn parse_error(text: &str) -> PlaError {
+        match PlaParser::parse(text.split('\n').map(String::from).collect()) {
+            Ok(_) => panic!("Expected {:?} to fail to parse", text),
+            Err(e) => e,
+        }
+    }
+
+    #[test]
+    fn it_should_return_an_io_error_for_a_missing_file() {
+        let result = PlaParser::new(Path::new("/nonexistent/schedule.pla"));
+        assert!(matches!(result, Err(PlaError::Io(_))));
+    }
+
+    #[test]
+    fn it_should_report_where_an_invalid_sub_block_is() {
+        let error = parse_error(r#"[89] Brewing Process
+
+    duration six
+"#);
+
+        match error {
+            PlaError::Syntax(e) => assert_eq!(
+                PlaParseError::new("unable to parse duration, expected a number of hours or a length such as `14d`, `90m` or `1w 2d`")
+                    .at(3, 14, "six")
+                    .with_source_line("    duration six"), e),
+            e => panic!("Expected a syntax error, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn it_should_report_an_invalid_entry_id() {
+        let error = parse_error("  [] Brewing Process");
+
+        match error {
+            PlaError::Syntax(e) => assert_eq!(
+                PlaParseError::new("unable to parse entry id as u32").at(1, 4, "").with_source_line("  [] Brewing Process"), e),
+            e => panic!("Expected a syntax error, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn it_should_report_a_sub_block_before_the_first_entry() {
+        let error = parse_error(r#"duration 6
+[89] Brewing Process
+"#);
+
+        match error {
+            PlaError::Semantic(e) => {
+                assert_eq!(1, e.line_number);
+                assert_eq!("`duration` block appears before the first entry", e.message);
+            },
+            e => panic!("Expected a semantic error, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn it_should_report_a_repeated_entry_id() {
+        let error = parse_error(r#"[89] Brewing Process
+    duration 6
+
+[89] Brewing Transfer
+"#);
+
+        assert_eq!("PlaParseError: line 4, column 1: entry 89 is already defined on line 1: `[89] Brewing Transfer`",
+                   format!("{}", error));
+    }
+
+    #[test]
+    fn it_should_point_diagnostics_at_the_offending_token() {
+        let error = parse_error(r#"[89] Brewing Process
+    start 2021-13-40 10
+"#);
+
+        match error {
+            PlaError::Syntax(e) => assert_eq!(r#"error: unable to parse date from string
+ --> <input>:2:11
+  |
+2 |     start 2021-13-40 10
+  |           ^^^^^^^^^^"#, format!("{}", PlaDiagnostic::from(&e))),
+            e => panic!("Expected a syntax error, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn it_should_point_violations_at_the_block_that_caused_them() {
+        let pla_parser = PlaParser::parse(r#"[89] Brewing Process
+    duration 6
+    dep 77
+"#.split('\n').map(String::from).collect()).unwrap();
+
+        let diagnostics = pla_parser.check();
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(r#"error: entry 89 depends on 77, which does not exist
+ --> <input>:3:5
+  |
+3 |     dep 77
+  |     ^^^^^^"#, format!("{}", diagnostics[0]));
+    }
+
+    #[test]
+    fn it_should_collect_every_error_in_a_lenient_parse() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut path_buf: PathBuf = PathBuf::new();
+        path_buf.push(dir);
+        path_buf.push("contrib");
+        path_buf.push("pla_complicated.pla");
+
+        let pla_parser = PlaParser::new(path_buf.as_path()).unwrap();
+        let contents = std::fs::read_to_string(path_buf.as_path()).unwrap();
+        let mut lines: Vec<String> = contents.split('\n').map(String::from).collect();
+        lines[21] = String::from("    start 2020-10-20 1x");
+        lines[1247] = String::from("    start 2021-01-8b 15");
+        lines[1253] = String::from("    duration O");
+
+        let (lenient_parser, errors) = PlaParser::parse_lenient(lines);
+        let error_lines: Vec<usize> = errors
+            .iter()
+            .map(|e| match e {
+                PlaError::Syntax(e) => e.line_number,
+                e => panic!("Expected a syntax error, got {:?}", e),
+            })
+            .collect();
+        assert_eq!(vec![22, 1248, 1254], error_lines);
+
+        // Only the malformed blocks are skipped, so every entry is still there
+        assert_eq!(pla_parser.entries.len(), lenient_parser.entries.len());
+    }
+
+    #[test]
+    fn it_should_skip_the_blocks_of_a_malformed_entry() {
+        let (pla_parser, errors) = PlaParser::parse_lenient(r#"[89] Brewing Process
+    duration 6
+
+[x90] Brewing Transfer
+    duration 2
+
+[89] Brewing Process Again
+    duration 3
+"#.split('\n').map(String::from).collect());
+
+        assert_eq!(1, pla_parser.entries.len());
+        assert_eq!(Duration::hours(6), pla_parser.entries[0].duration().unwrap().duration);
+
+        let messages: Vec<String> = errors.iter().map(|e| PlaDiagnostic::from(e).message).collect();
+        assert_eq!(vec!["expected an entry of the form `[id] description`", "entry 89 is already defined on line 1"], messages);
+    }
+
+    #[test]
+    fn it_should_warn_about_unknown_commands() {
+        let pla_parser = PlaParser::parse(r#"[89] Brewing Process
+    durtion 6
+    start 2020-10-20 16
+    wakka wakka
+"#.split('\n').map(String::from).collect()).unwrap();
+
+        assert!(pla_parser.get_entry_by_id(89).unwrap().duration().is_none());
+
+        let warnings = pla_parser.get_warnings();
+        assert_eq!(2, warnings.len());
+        assert_eq!(r#"warning: unknown command `durtion`, did you mean `duration`?
+ --> <input>:2:5
+  |
+2 |     durtion 6
+  |     ^^^^^^^"#, format!("{}", warnings[0]));
+        assert_eq!("unknown command `wakka`", warnings[1].message);
+        assert_eq!(4, warnings[1].line_number);
+    }
+
+    #[test]
+    fn it_should_reject_unknown_commands_when_strict() {
+        let pla_parser = PlaParser::parse(r#"[89] Brewing Process
+    durtion 6
+"#.split('\n').map(String::from).collect()).unwrap();
+
+        match pla_parser.strict() {
+            Err(PlaError::Syntax(e)) => assert_eq!(2, e.line_number),
+            _ => panic!("Expected an unknown command to be a syntax error"),
+        }
+    }
+
+    #[test]
+    fn it_should_write_text_that_parses_back_to_the_same_entries() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("contrib/pla_complicated.pla");
+        let pla_parser = PlaParser::new(&path).unwrap();
+        let written = pla_parser.to_string();
+        let reparsed: PlaParser = written.parse().unwrap();
+
+        assert_eq!(pla_parser.entries, reparsed.entries);
+        assert_eq!(format!("{:?}", pla_parser.entries), format!("{:?}", reparsed.entries));
+        assert_eq!(written, reparsed.to_string());
+    }
+
+    #[test]
+    fn it_should_write_every_command() {
+        let text = r#"[10000] Autumn's Early Arrival Blonde
+    child 89
+
+[89] Brewing Process
+    color #a0c8ff
+    dep 126
+    duration 6
+    progress 40
+    res Mash Tun 15G G10
+    start 2020-10-20 16
+
+[126]
+"#;
+
+        let pla_parser: PlaParser = text.parse().unwrap();
+        assert_eq!(text, pla_parser.to_string());
+    }
+
+    #[test]
+    fn it_should_keep_comments_as_notes_on_what_they_describe() {
+        let text = r#"// Autumn's Early Arrival Blonde
+[89] Brewing Process # first batch
+    # res Keg 1
+    color #a0c8ff
+    duration 6 // could be 7
+    # clean the tun afterwards
+
+# Kegging
+[90] Carbonation
+"#;
+
+        let pla_parser: PlaParser = text.parse().unwrap();
+        assert!(pla_parser.get_warnings().is_empty());
+
+        let entry = pla_parser.get_entry_by_id(89).unwrap();
+        assert!(entry.resources().is_empty());
+        assert_eq!(Some(String::from("#a0c8ff")), entry.get_color());
+
+        let entry_notes: Vec<&str> = entry.get_notes().iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(vec!["Autumn's Early Arrival Blonde", "first batch", "clean the tun afterwards"], entry_notes);
+
+        let blocks = &entry.blocks;
+        let color_notes: Vec<&str> = entry.get_block_notes(&blocks[0]).iter().map(|n| n.text.as_str()).collect();
+        let duration_notes: Vec<&str> = entry.get_block_notes(&blocks[1]).iter().map(|n| n.text.as_str()).collect();
+        assert_eq!(vec!["res Keg 1"], color_notes);
+        assert_eq!(vec!["could be 7"], duration_notes);
+        assert_eq!("Kegging", pla_parser.get_entry_by_id(90).unwrap().get_notes()[0].text);
+
+        let reparsed: PlaParser = pla_parser.to_string().parse().unwrap();
+        assert_eq!(pla_parser.to_string(), reparsed.to_string());
+        assert_eq!(3, reparsed.get_entry_by_id(89).unwrap().get_notes().len());
+        assert_eq!(5, reparsed.get_entry_by_id(89).unwrap().notes.len());
+    }
+
+    #[test]
+    fn it_should_accept_tabs_and_repeated_spaces_between_words() {
+        let pla_parser: PlaParser = "[89]\tBrewing Process\n\tstart  2020-10-20\t16\n  dep\t\t126\n    res   Mash Tun  # big one\n[126]".parse().unwrap();
+        let entry = pla_parser.get_entry_by_id(89).unwrap();
+
+        assert_eq!("Brewing Process", entry.description);
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(16, 0, 0)), entry.get_start_time());
+        assert_eq!(126, entry.dependencies()[0].dependency_id);
+        assert_eq!("Mash Tun", entry.resources()[0].resource_name);
+
+        let error = parse_error("[89] Brewing Process\n\tdep\t\tx");
+        assert_eq!("PlaParseError: line 2, column 7: Unable to parse dependency id from string: `x`", format!("{}", error));
+    }
+
+    #[test]
+    fn it_should_parse_from_a_string_or_a_reader() {
+        let text = r#"[89] Brewing Process
+    duration 6
+    start 2020-10-20 16
+"#;
+
+        let from_str: PlaParser = text.parse().unwrap();
+        let from_reader = PlaParser::from_reader(text.as_bytes()).unwrap();
+        assert_eq!(from_str.entries, from_reader.entries);
+        assert_eq!(Duration::hours(6), from_str.get_entry_by_id(89).unwrap().duration().unwrap().duration);
+
+        let (lenient_parser, diagnostics) = PlaParser::from_reader_lenient("[89] Brewing Process\n    duration x\n".as_bytes()).unwrap();
+        assert_eq!(1, lenient_parser.entries.len());
+        assert_eq!(2, diagnostics[0].line_number);
+        assert_eq!(None, diagnostics[0].file);
+    }
+}
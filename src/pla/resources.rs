@@ -0,0 +1,219 @@
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use chrono::NaiveDateTime;
+use crate::pla::entry::PlaEntry;
+use crate::pla::schedule::PlaSchedule;
+
+/// A single use of a resource by an entry.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaBooking {
+    pub entry_id: u32,
+
+    /// Line of the `res` block that made this booking.
+    pub line_number: usize,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+impl PlaBooking {
+    fn overlaps(&self, other: &PlaBooking) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+}
+
+/// Every booking of a single resource, ordered by start.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaResourceTimeline {
+    pub resource_name: String,
+    pub bookings: Vec<PlaBooking>,
+}
+
+impl PlaResourceTimeline {
+    /// Overlapping pairs of bookings made by different entries.
+    pub fn find_conflicts(&self) -> Vec<PlaResourceConflict> {
+        let mut conflicts: Vec<PlaResourceConflict> = vec![];
+        for (idx, first) in self.bookings.iter().enumerate() {
+            // Bookings are sorted by start, so once one starts after `first` ends, none of the
+            // remaining bookings can overlap it.
+            for second in self.bookings[idx + 1..].iter().take_while(|b| b.start <= first.end) {
+                if first.entry_id != second.entry_id && first.overlaps(second) {
+                    conflicts.push(PlaResourceConflict {
+                        resource_name: String::from(&self.resource_name),
+                        first: first.clone(),
+                        second: second.clone(),
+                        start: first.start.max(second.start),
+                        end: first.end.min(second.end),
+                    });
+                }
+            }
+        }
+
+        conflicts
+    }
+}
+
+/// Two entries booking the same resource at the same time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaResourceConflict {
+    pub resource_name: String,
+    pub first: PlaBooking,
+    pub second: PlaBooking,
+
+    /// Start of the interval during which both bookings hold the resource.
+    pub start: NaiveDateTime,
+
+    /// End of the interval during which both bookings hold the resource.
+    pub end: NaiveDateTime,
+}
+
+impl PlaResourceConflict {
+    /// Description of the conflict, without the line of the booking that caused it.
+    pub fn get_message(&self) -> String {
+        format!("resource {:?} is booked by entries {} and {} from {} to {}",
+                self.resource_name, self.first.entry_id, self.second.entry_id,
+                self.start.format("%Y-%m-%d %H:%M"), self.end.format("%Y-%m-%d %H:%M"))
+    }
+}
+
+impl Display for PlaResourceConflict {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: {}", self.second.line_number, self.get_message())
+    }
+}
+
+/// Build a timeline for every distinct resource named in a `res` block, in the order the
+/// resources first appear in the file. Entries that aren't scheduled don't book anything.
+pub fn build_timelines(entries: &[PlaEntry], schedule: &PlaSchedule) -> Vec<PlaResourceTimeline> {
+    let mut timelines: Vec<PlaResourceTimeline> = vec![];
+    for entry in entries {
+        let time = schedule.get(entry.id);
+        for resource in entry.resources() {
+            let idx = match timelines.iter().position(|t| t.resource_name == resource.resource_name) {
+                Some(idx) => idx,
+                None => {
+                    timelines.push(PlaResourceTimeline {
+                        resource_name: String::from(&resource.resource_name),
+                        bookings: vec![],
+                    });
+                    timelines.len() - 1
+                },
+            };
+
+            // An entry may list the same resource more than once, e.g. a transfer from a mash
+            // tun back into itself. That's still a single booking.
+            let timeline = &mut timelines[idx];
+            if let Some(t) = time {
+                if !timeline.bookings.iter().any(|b| b.entry_id == entry.id) {
+                    timeline.bookings.push(PlaBooking {
+                        entry_id: entry.id,
+                        line_number: resource.line_number,
+                        start: t.start,
+                        end: t.end,
+                    });
+                }
+            }
+        }
+    }
+
+    for timeline in &mut timelines {
+        timeline.bookings.sort_by_key(|b| (b.start, b.end));
+    }
+
+    timelines
+}
+
+/// Find every pair of entries that book the same resource at overlapping times. Zero-length
+/// bookings only conflict when they fall strictly inside another booking.
+pub fn find_conflicts(entries: &[PlaEntry], schedule: &PlaSchedule) -> Vec<PlaResourceConflict> {
+    build_timelines(entries, schedule)
+        .iter()
+        .flat_map(|t| t.find_conflicts())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use chrono::NaiveDate;
+    use crate::pla::parser::PlaParser;
+    use crate::pla::resources::{build_timelines, find_conflicts};
+
+    fn parse(text: &str) -> PlaParser {
+        PlaParser::parse(text.split('\n').map(String::from).collect()).unwrap()
+    }
+
+    #[test]
+    fn it_should_build_a_timeline_per_resource() {
+        let pla_parser = parse(r#"[90] Brewing Transfer
+    duration 0
+    start 2020-10-20 22
+    res Pumper
+    res Mash Tun 15G G10
+    res Mash Tun 15G G10
+
+[89] Brewing Process
+    duration 6
+    start 2020-10-20 16
+    res Mash Tun 15G G10
+"#);
+
+        let schedule = pla_parser.schedule().unwrap();
+        let timelines = build_timelines(&pla_parser.entries, &schedule);
+        let names: Vec<&str> = timelines.iter().map(|t| t.resource_name.as_str()).collect();
+        assert_eq!(vec!["Pumper", "Mash Tun 15G G10"], names);
+
+        let mash_tun_ids: Vec<u32> = timelines[1].bookings.iter().map(|b| b.entry_id).collect();
+        assert_eq!(vec![89, 90], mash_tun_ids);
+        assert!(timelines[1].find_conflicts().is_empty());
+    }
+
+    #[test]
+    fn it_should_report_overlapping_bookings_of_a_resource() {
+        let pla_parser = parse(r#"[240] Primary Fermentation Process (Fermentor 15G G10 3)
+    duration 224
+    start 2020-10-20 22
+    res Fermentor 15G G10 3
+
+[6] Secondary Fermentation Process (Fermentor 15G G10 3)
+    duration 48
+    start 2020-10-29 00
+    res Fermentor 15G G10 3
+
+[7] Secondary Fermentation Clean (Fermentor 15G G10 3)
+    duration 0
+    start 2020-10-31 00
+    res Fermentor 15G G10 3
+"#);
+
+        let schedule = pla_parser.schedule().unwrap();
+        let conflicts = find_conflicts(&pla_parser.entries, &schedule);
+        assert_eq!(1, conflicts.len());
+        assert_eq!(240, conflicts[0].first.entry_id);
+        assert_eq!(6, conflicts[0].second.entry_id);
+        assert_eq!(NaiveDate::from_ymd(2020, 10, 29).and_hms(0, 0, 0), conflicts[0].start);
+        assert_eq!(NaiveDate::from_ymd(2020, 10, 30).and_hms(6, 0, 0), conflicts[0].end);
+        assert_eq!("line 9: resource \"Fermentor 15G G10 3\" is booked by entries 240 and 6 from 2020-10-29 00:00 to 2020-10-30 06:00",
+                   format!("{}", conflicts[0]));
+    }
+
+    #[test]
+    fn it_should_find_double_booked_fermentors_in_the_complicated_pla_file() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut path_buf: PathBuf = PathBuf::new();
+        path_buf.push(dir);
+        path_buf.push("contrib");
+        path_buf.push("pla_complicated.pla");
+
+        let pla_parser = PlaParser::new(path_buf.as_path()).unwrap();
+        let schedule = pla_parser.schedule().unwrap();
+        let conflicts = find_conflicts(&pla_parser.entries, &schedule);
+        let conflict = conflicts
+            .iter()
+            .find(|c| c.first.entry_id == 36 && c.second.entry_id == 60)
+            .unwrap();
+
+        assert_eq!("Fermentor 15G G10 3", conflict.resource_name);
+        assert_eq!(NaiveDate::from_ymd(2020, 10, 16).and_hms(5, 0, 0), conflict.start);
+        assert_eq!(NaiveDate::from_ymd(2020, 10, 20).and_hms(22, 0, 0), conflict.end);
+    }
+}
@@ -0,0 +1,308 @@
+use std::collections::HashMap;
+use chrono::NaiveDateTime;
+use crate::pla::entry::PlaEntry;
+use crate::pla::error::PlaScheduleError;
+use crate::pla::time_zone::PlaTimeZone;
+use crate::pla::tree::PlaTree;
+
+/// Effective start and end of a single entry.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlaScheduledTime {
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+/// Effective start and end of every entry in a pla file.
+///
+/// Entries with scheduled children start at the earliest start of any child and end at the
+/// latest end of any child, recursively. Other entries use their own `start` block if they have
+/// one, and otherwise start as soon as every entry named in their `dep` blocks has ended. An
+/// entry's `duration` is added to its start to find its end.
+///
+/// Times are what clocks read in a single time zone. Start times written in another zone are
+/// converted to it, while those written without one are taken as they are.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaSchedule {
+    times: HashMap<u32, PlaScheduledTime>,
+}
+
+impl PlaSchedule {
+    pub fn new(entries: &[PlaEntry]) -> Result<PlaSchedule, PlaScheduleError> {
+        PlaSchedule::in_time_zone(entries, &PlaTimeZone::UTC)
+    }
+
+    pub fn in_time_zone(entries: &[PlaEntry], zone: &PlaTimeZone) -> Result<PlaSchedule, PlaScheduleError> {
+        // Building the tree validates the child blocks before we follow them.
+        PlaTree::new(entries)?;
+
+        let mut builder = ScheduleBuilder {
+            entries: entries.iter().map(|e| (e.id, e)).collect(),
+            resolved: HashMap::new(),
+            path: vec![],
+            zone: *zone,
+        };
+
+        for entry in entries {
+            builder.resolve(entry.id)?;
+        }
+
+        let times: HashMap<u32, PlaScheduledTime> = builder.resolved
+            .into_iter()
+            .filter_map(|(id, time)| time.map(|t| (id, t)))
+            .collect();
+
+        Ok(PlaSchedule {
+            times
+        })
+    }
+
+    pub fn get(&self, id: u32) -> Option<PlaScheduledTime> {
+        self.times.get(&id).copied()
+    }
+
+    pub fn get_start_time(&self, id: u32) -> Option<NaiveDateTime> {
+        self.get(id).map(|t| t.start)
+    }
+
+    pub fn get_end_time(&self, id: u32) -> Option<NaiveDateTime> {
+        self.get(id).map(|t| t.end)
+    }
+
+    /// Earliest start and latest end across all scheduled entries.
+    pub fn get_extent(&self) -> Option<PlaScheduledTime> {
+        let start = self.times.values().map(|t| t.start).min()?;
+        let end = self.times.values().map(|t| t.end).max()?;
+
+        Some(PlaScheduledTime {
+            start,
+            end
+        })
+    }
+}
+
+/// Memoized, depth-first resolution of entry times. `path` holds the entries currently being
+/// resolved so that an entry which ends up depending on itself is reported instead of recursing
+/// forever.
+struct ScheduleBuilder<'a> {
+    entries: HashMap<u32, &'a PlaEntry>,
+    resolved: HashMap<u32, Option<PlaScheduledTime>>,
+    path: Vec<u32>,
+    zone: PlaTimeZone,
+}
+
+impl<'a> ScheduleBuilder<'a> {
+    fn resolve(&mut self, id: u32) -> Result<Option<PlaScheduledTime>, PlaScheduleError> {
+        if let Some(time) = self.resolved.get(&id) {
+            return Ok(*time);
+        }
+
+        if let Some(pos) = self.path.iter().position(|p| *p == id) {
+            let mut cycle: Vec<u32> = self.path[pos..].to_vec();
+            cycle.push(id);
+            return Err(PlaScheduleError::DependencyCycle { path: cycle });
+        }
+
+        // Dangling ids are left unscheduled here; they're reported by validation instead.
+        let entry: &PlaEntry = match self.entries.get(&id) {
+            Some(e) => e,
+            None => return Ok(None),
+        };
+
+        self.path.push(id);
+        let time = self.resolve_entry(entry)?;
+        self.path.pop();
+
+        self.resolved.insert(id, time);
+        Ok(time)
+    }
+
+    fn resolve_entry(&mut self, entry: &PlaEntry) -> Result<Option<PlaScheduledTime>, PlaScheduleError> {
+        let mut child_times: Vec<PlaScheduledTime> = vec![];
+        for child in entry.children() {
+            if let Some(t) = self.resolve(child.child_id)? {
+                child_times.push(t);
+            }
+        }
+
+        let child_start = child_times.iter().map(|t| t.start).min();
+        let child_end = child_times.iter().map(|t| t.end).max();
+        if let (Some(start), Some(end)) = (child_start, child_end) {
+            return Ok(Some(PlaScheduledTime { start, end }));
+        }
+
+        if entry.start().is_some() {
+            return match (entry.get_start_time_in(&self.zone), entry.get_end_time_in(&self.zone)) {
+                (Some(start), Some(end)) => Ok(Some(PlaScheduledTime { start, end })),
+                _ => Err(PlaScheduleError::OutOfRange { id: entry.id }),
+            };
+        }
+
+        let dependency_ids: Vec<u32> = entry
+            .dependencies()
+            .iter()
+            .map(|d| d.dependency_id)
+            .filter(|d| self.entries.contains_key(d))
+            .collect();
+
+        if dependency_ids.is_empty() {
+            return Ok(None);
+        }
+
+        let mut start: Option<NaiveDateTime> = None;
+        for dependency_id in dependency_ids {
+            match self.resolve(dependency_id)? {
+                Some(t) => start = start.max(Some(t.end)),
+
+                // We can't place an entry until we know when everything it depends on ends.
+                None => return Ok(None),
+            }
+        }
+
+        match start.map(|s| (s, s.checked_add_signed(entry.get_length()))) {
+            Some((start, Some(end))) => Ok(Some(PlaScheduledTime { start, end })),
+            Some((_, None)) => Err(PlaScheduleError::OutOfRange { id: entry.id }),
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use chrono::NaiveDate;
+    use crate::pla::parser::PlaParser;
+    use crate::pla::schedule::PlaSchedule;
+    use crate::pla::error::PlaScheduleError;
+    use crate::pla::fixtures::{get_dependent_task, get_entry, get_parent, get_task};
+
+    #[test]
+    fn it_should_schedule_parents_from_their_children_recursively() {
+        let entries = vec![
+            get_parent(10000, "Parent 10000", vec![1, 20]),
+            get_parent(20, "Parent 20", vec![2, 3]),
+            get_task(1, "Task 1", "start 2020-10-20 16", "duration 6"),
+            get_task(2, "Task 2", "start 2020-10-21 0", "duration 48"),
+            get_task(3, "Task 3", "start 2020-10-19 12", "duration 2"),
+            get_parent(126, "Parent 126", vec![]),
+        ];
+
+        let schedule = PlaSchedule::new(&entries).unwrap();
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 19).and_hms(12, 0, 0)), schedule.get_start_time(20));
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 23).and_hms(0, 0, 0)), schedule.get_end_time(20));
+        assert_eq!(schedule.get(20), schedule.get(10000));
+        assert_eq!(schedule.get(10000), schedule.get_extent());
+        assert_eq!(None, schedule.get(126));
+    }
+
+    #[test]
+    fn it_should_cascade_start_times_through_dependencies() {
+        let entries = vec![
+            get_dependent_task(91, "Task 91", "duration 1", vec![90]),
+            get_task(89, "Task 89", "start 2020-10-20 16", "duration 6"),
+            get_dependent_task(90, "Task 90", "duration 2", vec![89]),
+            get_task(100, "Task 100", "start 2020-10-20 12", "duration 20"),
+            get_dependent_task(240, "Task 240", "duration 224", vec![91, 100]),
+        ];
+
+        let schedule = PlaSchedule::new(&entries).unwrap();
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(22, 0, 0)), schedule.get_start_time(90));
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 21).and_hms(0, 0, 0)), schedule.get_start_time(91));
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 21).and_hms(8, 0, 0)), schedule.get_start_time(240));
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 30).and_hms(16, 0, 0)), schedule.get_end_time(240));
+    }
+
+    #[test]
+    fn it_should_schedule_start_times_from_other_zones_in_the_chosen_zone() {
+        let entries = vec![
+            get_task(89, "Task 89", "start 2021-07-01 09:00 America/Chicago", "duration 6"),
+            get_task(90, "Task 90", "start 2021-07-01 12:30", "duration 2"),
+            get_dependent_task(91, "Task 91", "duration 1", vec![89, 90]),
+        ];
+
+        let schedule = PlaSchedule::in_time_zone(&entries, &"Europe/London".parse().unwrap()).unwrap();
+        assert_eq!(Some(NaiveDate::from_ymd(2021, 7, 1).and_hms(15, 0, 0)), schedule.get_start_time(89));
+        assert_eq!(Some(NaiveDate::from_ymd(2021, 7, 1).and_hms(12, 30, 0)), schedule.get_start_time(90));
+        assert_eq!(Some(NaiveDate::from_ymd(2021, 7, 1).and_hms(21, 0, 0)), schedule.get_start_time(91));
+    }
+
+    #[test]
+    fn it_should_prefer_an_explicit_start_over_dependencies() {
+        let entries = vec![
+            get_task(89, "Task 89", "start 2020-10-20 16", "duration 6"),
+            get_entry(90, "Task 90", &["start 2020-10-25 0", "duration 2", "dep 89"]),
+        ];
+
+        let schedule = PlaSchedule::new(&entries).unwrap();
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 25).and_hms(0, 0, 0)), schedule.get_start_time(90));
+    }
+
+    #[test]
+    fn it_should_leave_entries_with_unscheduled_dependencies_unscheduled() {
+        let entries = vec![
+            get_dependent_task(90, "Task 90", "duration 2", vec![89]),
+            get_dependent_task(91, "Task 91", "duration 2", vec![12345]),
+            get_parent(89, "Parent 89", vec![]),
+        ];
+
+        let schedule = PlaSchedule::new(&entries).unwrap();
+        assert_eq!(None, schedule.get(90));
+        assert_eq!(None, schedule.get(91));
+    }
+
+    #[test]
+    fn it_should_report_a_dependency_cycle() {
+        let entries = vec![
+            get_dependent_task(89, "Task 89", "duration 6", vec![91]),
+            get_dependent_task(90, "Task 90", "duration 2", vec![89]),
+            get_dependent_task(91, "Task 91", "duration 1", vec![90]),
+        ];
+
+        assert_eq!(Err(PlaScheduleError::DependencyCycle { path: vec![89, 91, 90, 89] }), PlaSchedule::new(&entries));
+    }
+
+    #[test]
+    fn it_should_report_an_entry_that_ends_too_late_to_schedule() {
+        let entries = vec![
+            get_task(89, "Task 89", "start 2020-10-20 16", "duration 6"),
+            get_dependent_task(90, "Task 90", "duration 1w", vec![89]),
+        ];
+        assert!(PlaSchedule::new(&entries).is_ok());
+
+        let entries = vec![get_task(89, "Task 89", "start +262143-12-31 16", "duration 1w")];
+        assert_eq!(Err(PlaScheduleError::OutOfRange { id: 89 }), PlaSchedule::new(&entries));
+    }
+
+    #[test]
+    fn it_should_report_an_entry_that_starts_too_late_to_schedule_in_a_zone() {
+        let entries = vec![get_task(89, "Task 89", "start +262143-12-30 23:00 -05:00", "duration 0")];
+        assert!(PlaSchedule::in_time_zone(&entries, &"-05:00".parse().unwrap()).is_err());
+        assert_eq!(
+            Err(PlaScheduleError::OutOfRange { id: 89 }),
+            PlaSchedule::in_time_zone(&entries, &"+05:00".parse().unwrap()),
+        );
+    }
+
+    #[test]
+    fn it_should_report_a_cycle_through_a_parent() {
+        let entries = vec![
+            get_parent(10000, "Parent 10000", vec![89]),
+            get_dependent_task(89, "Task 89", "duration 6", vec![10000]),
+        ];
+
+        assert_eq!(Err(PlaScheduleError::DependencyCycle { path: vec![10000, 89, 10000] }), PlaSchedule::new(&entries));
+    }
+
+    #[test]
+    fn it_should_schedule_the_batches_of_a_complicated_pla_file() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut path_buf: PathBuf = PathBuf::new();
+        path_buf.push(dir);
+        path_buf.push("contrib");
+        path_buf.push("pla_complicated.pla");
+
+        let pla_parser = PlaParser::new(path_buf.as_path()).unwrap();
+        let schedule = pla_parser.schedule().unwrap();
+        assert_eq!(Some(NaiveDate::from_ymd(2020, 10, 20).and_hms(16, 0, 0)), schedule.get_start_time(10000));
+        assert!(schedule.get_end_time(10000).unwrap() > schedule.get_start_time(10000).unwrap());
+    }
+}
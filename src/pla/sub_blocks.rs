@@ -1,538 +1,564 @@
-use std::fmt::{Debug, Formatter};
-use chrono::NaiveDate;
-use mopa::mopafy;
+use std::fmt::{Debug, Display, Formatter};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike};
+use chrono::naive::{MAX_DATE, MIN_DATE};
 use crate::pla::command::PlaCommand;
-use crate::pla::error::{PlaSubBlockConversionError, PlaParseError};
-use crate::pla::parser::{HeirarchicalPlaLine};
-
-use dyn_clone::{clone_trait_object, DynClone};
-use regex::Regex;
+use crate::pla::error::PlaParseError;
+use crate::pla::syntax::PlaBlockNode;
+use crate::pla::time_zone::PlaTimeZone;
 
+/// Parses a block from a single line of text, such as `duration 6`, for the entry `parent_id`.
 #[macro_export]
-macro_rules! try_from_box {
+macro_rules! try_from_str {
     ( $x:ident ) => {
-        impl TryFrom<Box<dyn PlaSubBlock>> for $x {
-            type Error = PlaSubBlockConversionError;
+        impl TryFrom<(u32, &str)> for $x {
+            type Error = PlaParseError;
 
-            fn try_from(value: Box<dyn PlaSubBlock>) -> Result<Self, Self::Error> {
-                $x::try_from(&value)
+            fn try_from(value: (u32, &str)) -> Result<Self, Self::Error> {
+                let (parent_id, input) = value;
+                $x::try_from((parent_id, &input.parse::<PlaBlockNode>()?))
             }
         }
     };
 }
 
-#[macro_export]
-macro_rules! box_from_upcast {
-    ( $x:ident, $hl:expr ) => {
-        Box::new($x::try_from(&$hl).unwrap()) as Box<dyn PlaSubBlock>
-    };
-}
-
-#[macro_export]
-macro_rules! push_entry_sub_block {
-    ( $x:ident, $sb:expr, $entry_sb:expr) => {
-        let block = $x::try_from($sb);
-        if block.is_err() {
-            panic!("Encountered an error while trying to create hierarchical entries: {}", block.err().unwrap());
-        }
-        $entry_sb.push(Box::new(block.unwrap()));
-    };
-}
-
-pub trait PlaSubBlock: mopa::Any + DynClone {
-    fn get_command(&self) -> PlaCommand;
-    fn get_parent_id(&self) -> u32;
+/// Length of a single part of a duration, such as `14d` or `90m`. Numbers without a unit are hours.
+fn parse_length(token: &str) -> Option<Duration> {
+    let split = token.find(|c: char| !c.is_ascii_digit()).unwrap_or(token.len());
+    let (number, unit) = token.split_at(split);
+    let number = number.parse::<u32>().ok()? as i64;
+
+    match unit {
+        "m" => Some(Duration::minutes(number)),
+        "" | "h" => Some(Duration::hours(number)),
+        "d" => Some(Duration::days(number)),
+        "w" => Some(Duration::weeks(number)),
+        _ => None,
+    }
 }
 
-clone_trait_object!(PlaSubBlock);
+/// Durations as a whole number of minutes, the smallest unit they can be written in.
+#[cfg(feature = "serde")]
+pub(crate) mod minutes {
+    use chrono::Duration;
+    use serde::{Deserialize, Deserializer, Serializer};
 
-mopafy!(PlaSubBlock);
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_i64(duration.num_minutes())
+    }
 
-impl std::fmt::Debug for Box<dyn PlaSubBlock> {
-    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        let str_rep = match self.get_command() {
-            PlaCommand::START => {
-                let start_res = PlaStartBlock::try_from(self);
-                if start_res.is_ok() {
-                    let start = start_res.unwrap();
-                    String::from(format!("START {:?} {:?}", start.date, start.hour))
-                } else {
-                    String::from("ERROR")
-                }
-            },
-            PlaCommand::CHILD => {
-                let child_res = PlaChildBlock::try_from(self);
-                if child_res.is_ok() {
-                    let child = child_res.unwrap();
-                    String::from(format!("CHILD {:?}", child.child_id))
-                } else {
-                    String::from("ERROR")
-                }
-            },
-            PlaCommand::DURATION => {
-                let duration_res = PlaDurationBlock::try_from(self);
-                if duration_res.is_ok() {
-                    let duration = duration_res.unwrap();
-                    String::from(format!("DURATION {:?}", duration.duration))
-                } else {
-                    String::from("ERROR")
-                }
-            },
-            PlaCommand::DEPENDENCY => {
-                let dependency_res = PlaDependencyBlock::try_from(self);
-                if dependency_res.is_ok() {
-                    let dependency = dependency_res.unwrap();
-                    String::from(format!("DEPENDENCY {:?}", dependency.dependency_id))
-                } else {
-                    String::from("ERROR")
-                }
-            },
-            PlaCommand::RESOURCE => {
-                let res_res = PlaResourceBlock::try_from(self);
-                if res_res.is_ok() {
-                    let resource = res_res.unwrap();
-                    String::from(format!("RESOURCE {:?}", resource.resource_name))
-                } else {
-                    String::from("ERROR")
-                }
-            },
-            _ => String::from("ERROR")
-        };
-        write!(f, "{:?}", &str_rep)
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        i64::deserialize(deserializer).map(Duration::minutes)
     }
 }
 
-#[derive(Clone, Debug)]
-pub struct PlaResourceBlock {
-    pub parent_id: u32,
-    pub resource_name: String
+/// A line within an entry. Displays as that line, without indentation, in the form it would be
+/// written in a pla file.
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum PlaSubBlock {
+    Child(PlaChildBlock),
+    Color(PlaColorBlock),
+    Dependency(PlaDependencyBlock),
+    Duration(PlaDurationBlock),
+    Progress(PlaProgressBlock),
+    Resource(PlaResourceBlock),
+    Start(PlaStartBlock),
 }
 
-impl PlaSubBlock for PlaResourceBlock {
-    fn get_command(&self) -> PlaCommand {
-        PlaCommand::RESOURCE
+impl PlaSubBlock {
+    pub fn get_command(&self) -> PlaCommand {
+        match self {
+            PlaSubBlock::Child(_) => PlaCommand::CHILD,
+            PlaSubBlock::Color(_) => PlaCommand::COLOR,
+            PlaSubBlock::Dependency(_) => PlaCommand::DEPENDENCY,
+            PlaSubBlock::Duration(_) => PlaCommand::DURATION,
+            PlaSubBlock::Progress(_) => PlaCommand::PROGRESS,
+            PlaSubBlock::Resource(_) => PlaCommand::RESOURCE,
+            PlaSubBlock::Start(_) => PlaCommand::START,
+        }
     }
 
-    fn get_parent_id(&self) -> u32 {
-        self.parent_id
+    pub fn get_parent_id(&self) -> u32 {
+        match self {
+            PlaSubBlock::Child(b) => b.parent_id,
+            PlaSubBlock::Color(b) => b.parent_id,
+            PlaSubBlock::Dependency(b) => b.parent_id,
+            PlaSubBlock::Duration(b) => b.parent_id,
+            PlaSubBlock::Progress(b) => b.parent_id,
+            PlaSubBlock::Resource(b) => b.parent_id,
+            PlaSubBlock::Start(b) => b.parent_id,
+        }
     }
-}
-
-try_from_box!{PlaResourceBlock}
-
-impl TryFrom<&Box<dyn PlaSubBlock>> for PlaResourceBlock {
-    type Error = PlaSubBlockConversionError;
 
-    fn try_from(value: &Box<dyn PlaSubBlock>) -> Result<Self, Self::Error> {
-        if value.get_command() != PlaCommand::RESOURCE {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::RESOURCE
-            });
+    /// 1-based line within the source file, or 0 if the block was not parsed from a file.
+    pub fn get_line_number(&self) -> usize {
+        match self {
+            PlaSubBlock::Child(b) => b.line_number,
+            PlaSubBlock::Color(b) => b.line_number,
+            PlaSubBlock::Dependency(b) => b.line_number,
+            PlaSubBlock::Duration(b) => b.line_number,
+            PlaSubBlock::Progress(b) => b.line_number,
+            PlaSubBlock::Resource(b) => b.line_number,
+            PlaSubBlock::Start(b) => b.line_number,
         }
+    }
+}
 
-        let converted_opt: Option<&PlaResourceBlock> = value.downcast_ref::<PlaResourceBlock>();
-
-        if converted_opt.is_none() {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::RESOURCE
-            });
+impl Display for PlaSubBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlaSubBlock::Child(b) => write!(f, "{}", b),
+            PlaSubBlock::Color(b) => write!(f, "{}", b),
+            PlaSubBlock::Dependency(b) => write!(f, "{}", b),
+            PlaSubBlock::Duration(b) => write!(f, "{}", b),
+            PlaSubBlock::Progress(b) => write!(f, "{}", b),
+            PlaSubBlock::Resource(b) => write!(f, "{}", b),
+            PlaSubBlock::Start(b) => write!(f, "{}", b),
         }
-
-        let dc_ref = converted_opt.unwrap();
-        Ok(PlaResourceBlock {
-            parent_id: dc_ref.parent_id,
-            resource_name: String::from(&dc_ref.resource_name)
-        })
     }
 }
 
-impl TryFrom<&HeirarchicalPlaLine> for PlaResourceBlock {
-    type Error = PlaParseError;
+/// Shows what the block holds, but not where it was written, so that the same blocks parsed from
+/// different text compare equal.
+impl Debug for PlaSubBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let str_rep = match self {
+            PlaSubBlock::Start(start) => format!("START {:?} {:?}", start.time, start.zone),
+            PlaSubBlock::Child(child) => format!("CHILD {:?}", child.child_id),
+            PlaSubBlock::Duration(duration) => format!("DURATION {:?}", duration.duration),
+            PlaSubBlock::Dependency(dependency) => format!("DEPENDENCY {:?}", dependency.dependency_id),
+            PlaSubBlock::Resource(resource) => format!("RESOURCE {:?}", resource.resource_name),
+            PlaSubBlock::Progress(progress) => format!("PROGRESS {:?}", progress.progress),
+            PlaSubBlock::Color(color) => format!("COLOR {:?}", color.color),
+        };
+        write!(f, "{:?}", &str_rep)
+    }
+}
 
-    fn try_from(value: &HeirarchicalPlaLine) -> Result<Self, Self::Error> {
-        let str_command = value.text.trim_start();
-        match value.parent_id {
-            Some(x) => {
-                match PlaResourceBlock::try_from((x, str_command)) {
-                    Ok(x) => Ok(x),
-                    Err(_) => Err(PlaParseError{ message: String::from("resource block parsing failed")}),
-                }
-            },
-            None => Err(PlaParseError{ message: String::from("Unable to parse HeirarchicalPlaLine without parent id as PlaResourceBlock") }),
+macro_rules! sub_block_from {
+    ( $variant:ident, $x:ident ) => {
+        impl From<$x> for PlaSubBlock {
+            fn from(block: $x) -> Self {
+                PlaSubBlock::$variant(block)
+            }
         }
-    }
+    };
 }
 
-impl TryFrom<(u32, &str)> for PlaResourceBlock {
-    type Error = PlaParseError;
+sub_block_from!{Child, PlaChildBlock}
+sub_block_from!{Color, PlaColorBlock}
+sub_block_from!{Dependency, PlaDependencyBlock}
+sub_block_from!{Duration, PlaDurationBlock}
+sub_block_from!{Progress, PlaProgressBlock}
+sub_block_from!{Resource, PlaResourceBlock}
+sub_block_from!{Start, PlaStartBlock}
 
-    fn try_from(value: (u32, &str)) -> Result<Self, Self::Error> {
-        let (parent_id, command_text) = value;
-        let res_re = Regex::new(r"res(\s)+(.*)").unwrap();
-        let captures = res_re.captures(command_text).unwrap();
-        let resource_name = format!("{}", &captures[2]);
+/// Parses whichever kind of block the node's command names.
+impl TryFrom<(u32, &PlaBlockNode)> for PlaSubBlock {
+    type Error = PlaParseError;
 
-        return Ok(PlaResourceBlock {
-            parent_id,
-            resource_name
-        });
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (_, node) = value;
+        match node.command.text.parse::<PlaCommand>()? {
+            PlaCommand::CHILD => PlaChildBlock::try_from(value).map(PlaSubBlock::Child),
+            PlaCommand::COLOR => PlaColorBlock::try_from(value).map(PlaSubBlock::Color),
+            PlaCommand::DEPENDENCY => PlaDependencyBlock::try_from(value).map(PlaSubBlock::Dependency),
+            PlaCommand::DURATION => PlaDurationBlock::try_from(value).map(PlaSubBlock::Duration),
+            PlaCommand::PROGRESS => PlaProgressBlock::try_from(value).map(PlaSubBlock::Progress),
+            PlaCommand::RESOURCE => PlaResourceBlock::try_from(value).map(PlaSubBlock::Resource),
+            PlaCommand::START => PlaStartBlock::try_from(value).map(PlaSubBlock::Start),
+            _ => Err(node.command.error(&format!("unknown command `{}`", node.command.text))),
+        }
     }
 }
 
-#[derive(Clone, Copy, Debug)]
-pub struct PlaDependencyBlock {
+try_from_str!{PlaSubBlock}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaResourceBlock {
     pub parent_id: u32,
-    pub dependency_id: u32
+    pub line_number: usize,
+    pub resource_name: String
 }
 
-impl PlaSubBlock for PlaDependencyBlock {
-    fn get_command(&self) -> PlaCommand {
-        PlaCommand::DEPENDENCY
-    }
-
-    fn get_parent_id(&self) -> u32 {
-        self.parent_id
+impl Display for PlaResourceBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", PlaCommand::RESOURCE, self.resource_name)
     }
 }
 
-impl TryFrom<&Box<dyn PlaSubBlock>> for PlaDependencyBlock {
-    type Error = PlaSubBlockConversionError;
-
-    fn try_from(value: &Box<dyn PlaSubBlock>) -> Result<Self, Self::Error> {
-        if value.get_command() != PlaCommand::DEPENDENCY {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::DEPENDENCY
-            });
-        }
-
-        let converted_opt: Option<&PlaDependencyBlock> = value.downcast_ref::<PlaDependencyBlock>();
+impl TryFrom<(u32, &PlaBlockNode)> for PlaResourceBlock {
+    type Error = PlaParseError;
 
-        if converted_opt.is_none() {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::DEPENDENCY
-            });
-        }
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (parent_id, node) = value;
+        let resource_name = match node.rest(0) {
+            Some(name) => name.text,
+            None => return Err(node.missing("unable to parse resource name for res command")),
+        };
 
-        let dc_ref = converted_opt.unwrap();
-        Ok(PlaDependencyBlock {
-            parent_id: dc_ref.parent_id,
-            dependency_id: dc_ref.dependency_id
+        Ok(PlaResourceBlock {
+            parent_id,
+            line_number: node.span.start.line_number,
+            resource_name
         })
     }
 }
 
-try_from_box!{PlaDependencyBlock}
+try_from_str!{PlaResourceBlock}
 
-impl TryFrom<&HeirarchicalPlaLine> for PlaDependencyBlock {
-    type Error = PlaParseError;
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaDependencyBlock {
+    pub parent_id: u32,
+    pub line_number: usize,
+    pub dependency_id: u32
+}
 
-    fn try_from(value: &HeirarchicalPlaLine) -> Result<Self, Self::Error> {
-        let str_command = value.text.trim_start();
-        match value.parent_id {
-            Some(x) => {
-                match PlaDependencyBlock::try_from((x, str_command)) {
-                    Ok(x) => Ok(x),
-                    Err(_) => Err(PlaParseError{ message: String::from("dependency block parsing failed")}),
-                }
-            },
-            None => Err(PlaParseError{ message: String::from("Unable to parse HeirarchicalPlaLine without parent id as PlaDependencyBlock") }),
-        }
+impl Display for PlaDependencyBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", PlaCommand::DEPENDENCY, self.dependency_id)
     }
 }
 
-impl TryFrom<(u32, &str)> for PlaDependencyBlock {
+impl TryFrom<(u32, &PlaBlockNode)> for PlaDependencyBlock {
     type Error = PlaParseError;
 
-    fn try_from(value: (u32, &str)) -> Result<Self, Self::Error> {
-        let (parent_id, command_text) = value;
-        let tokens: Vec<String> = command_text.split(' ').map(|s| String::from(s)).collect();
-
-        let str_dep_id: &String = match tokens.get(1) {
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (parent_id, node) = value;
+        let str_dep_id = match node.arguments.first() {
             Some(x) => x,
-            None => return Err(PlaParseError { message: String::from("Cannot parse dependency block without a dependency id") })
+            None => return Err(node.missing("Cannot parse dependency block without a dependency id"))
         };
 
-        let dependency_id = match str_dep_id.parse::<u32>() {
+        let dependency_id = match str_dep_id.text.parse::<u32>() {
             Ok(x) => x,
-            Err(_) => return Err(PlaParseError { message: String::from("Unable to parse dependency id from string")})
+            Err(_) => return Err(str_dep_id.error("Unable to parse dependency id from string"))
         };
 
-        return Ok(PlaDependencyBlock {
+        Ok(PlaDependencyBlock {
             parent_id,
+            line_number: node.span.start.line_number,
             dependency_id
-        });
+        })
     }
 }
 
+try_from_str!{PlaDependencyBlock}
+
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlaStartBlock {
     pub parent_id: u32,
-    pub date: NaiveDate,
-    pub hour: u32,
-}
+    pub line_number: usize,
 
-impl PlaSubBlock for PlaStartBlock {
-    fn get_command(&self) -> PlaCommand {
-        PlaCommand::START
-    }
+    /// Date and time of day, as written.
+    pub time: NaiveDateTime,
 
-    fn get_parent_id(&self) -> u32 {
-        self.parent_id
-    }
+    /// Time zone that `time` is written in. Times without one are the same in every zone.
+    pub zone: Option<PlaTimeZone>,
 }
 
-impl TryFrom<&Box<dyn PlaSubBlock>> for PlaStartBlock {
-    type Error = PlaSubBlockConversionError;
-
-    fn try_from(value: &Box<dyn PlaSubBlock>) -> Result<Self, Self::Error> {
-        if value.get_command() != PlaCommand::START {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::START
-            });
+impl PlaStartBlock {
+    /// What clocks in `zone` read when this block starts, or `None` if the time it's written in
+    /// can't be converted to `zone`.
+    pub fn get_time_in(&self, zone: &PlaTimeZone) -> Option<NaiveDateTime> {
+        match self.zone {
+            Some(z) => z.to_utc(&self.time).and_then(|utc| zone.from_utc(&utc)),
+            None => Some(self.time),
         }
-
-        let converted_opt: Option<&PlaStartBlock> = value.downcast_ref::<PlaStartBlock>();
-
-        if converted_opt.is_none() {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::START
-            });
-        }
-
-        let dc_ref = converted_opt.unwrap();
-        Ok(PlaStartBlock {
-            parent_id: dc_ref.parent_id,
-            date: dc_ref.date.clone(),
-            hour: dc_ref.hour
-        })
     }
 }
 
-try_from_box!{PlaStartBlock}
-
-impl TryFrom<&HeirarchicalPlaLine> for PlaStartBlock {
-    type Error = PlaParseError;
+impl Display for PlaStartBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Whole hours are written as just the hour, as the upstream format expects.
+        let format = if self.time.minute() == 0 { "%Y-%m-%d %H" } else { "%Y-%m-%d %H:%M" };
+        write!(f, "{} {}", PlaCommand::START, self.time.format(format))?;
 
-    fn try_from(value: &HeirarchicalPlaLine) -> Result<Self, Self::Error> {
-        let str_command = value.text.trim_start();
-        match value.parent_id {
-            Some(x) => {
-                match PlaStartBlock::try_from((x, str_command)) {
-                    Ok(x) => Ok(x),
-                    Err(_) => Err(PlaParseError{ message: String::from("start block parsing failed")}),
-                }
-            },
-            None => Err(PlaParseError{ message: String::from("Unable to parse HeirarchicalPlaLine without parent id as PlaStartBlock") }),
+        match &self.zone {
+            Some(zone) => write!(f, " {}", zone),
+            None => Ok(()),
         }
     }
 }
 
-impl TryFrom<(u32, &str)> for PlaStartBlock {
+impl TryFrom<(u32, &PlaBlockNode)> for PlaStartBlock {
     type Error = PlaParseError;
 
-    fn try_from(value: (u32, &str)) -> Result<Self, Self::Error> {
-        let (parent_id, command_text) = value;
-        let tokens: Vec<String> = command_text.split(' ').map(|s| String::from(s)).collect();
-
-        let str_date: String = match tokens.get(1) {
-            Some(x) => String::from(x),
-            None => return Err(PlaParseError { message: String::from("unable to parse date for start command") }),
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (parent_id, node) = value;
+        let str_date = match node.arguments.first() {
+            Some(x) => x,
+            None => return Err(node.missing("unable to parse date for start command")),
         };
 
-        let date: NaiveDate = match NaiveDate::parse_from_str(&str_date, "%Y-%m-%d") {
+        let date: NaiveDate = match NaiveDate::parse_from_str(&str_date.text, "%Y-%m-%d") {
             Ok(x) => x,
-            Err(_e) => return Err(PlaParseError { message: String::from("unable to parse date from string") })
+            Err(_e) => return Err(str_date.error("unable to parse date from string"))
         };
 
-        // Default to midnight if an hour isn't provided.
-        let str_hour = match tokens.get(2) {
-            Some(x) => x,
-            None => "0"
+        // Default to midnight if a time isn't provided. Times are either `HH:MM` or a number of
+        // hours after midnight.
+        let str_time = node.arguments.get(1);
+        let time: NaiveDateTime = match str_time {
+            None => date.and_hms(0, 0, 0),
+            Some(x) if x.text.contains(':') => match NaiveTime::parse_from_str(&x.text, "%H:%M") {
+                Ok(y) => date.and_time(y),
+                Err(_e) => return Err(x.error("unable to parse time of day in start sub block"))
+            },
+            Some(x) => {
+                // Hours past midnight may run into later days, but not past the dates chrono holds
+                let hours = x.text.parse::<u32>().ok().map(|y| Duration::hours(y as i64));
+                match hours.and_then(|h| date.and_hms(0, 0, 0).checked_add_signed(h)) {
+                    Some(y) => y,
+                    None => return Err(x.error("unable to parse hour of day in start sub block"))
+                }
+            },
         };
 
-        let hour = match str_hour.parse::<u32>() {
-            Ok(x) => x,
-            Err(_e) => return Err(PlaParseError { message: String::from("unable to parse hour of day in start sub block") })
+        let zone: Option<PlaTimeZone> = match node.arguments.get(2) {
+            Some(x) => match x.text.parse::<PlaTimeZone>() {
+                Ok(zone) => Some(zone),
+                Err(e) => return Err(x.error(&e.message)),
+            },
+            None => None,
         };
 
-        return Ok(PlaStartBlock {
-            date,
-            hour,
-            parent_id
-        });
+        if let (Some(z), Some(x)) = (zone, str_time) {
+            if !PlaTimeZone::can_convert(&time) {
+                return Err(x.error("start time is too close to the limits of the calendar to convert between time zones"));
+            }
+
+            if z.to_utc(&time).is_none() {
+                return Err(x.error(&format!("clocks in {} skip this time", z)));
+            }
+        }
+
+        Ok(PlaStartBlock {
+            time,
+            zone,
+            parent_id,
+            line_number: node.span.start.line_number,
+        })
     }
 }
 
+try_from_str!{PlaStartBlock}
+
 #[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct PlaDurationBlock {
     pub parent_id: u32,
-    pub duration: u32,
+    pub line_number: usize,
+
+    /// How long the entry takes. Written as a number of hours, or as lengths with units, e.g.
+    /// `1w 2d`, which are added together.
+    #[cfg_attr(feature = "serde", serde(with = "minutes"))]
+    pub duration: Duration,
 }
 
-try_from_box!{PlaDurationBlock}
+impl Display for PlaDurationBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        // Whole hours are written without a unit, as the upstream format expects.
+        let minutes = self.duration.num_minutes();
+        if minutes % 60 == 0 {
+            write!(f, "{} {}", PlaCommand::DURATION, minutes / 60)
+        } else {
+            write!(f, "{} {}m", PlaCommand::DURATION, minutes)
+        }
+    }
+}
 
-impl TryFrom<&Box<dyn PlaSubBlock>> for PlaDurationBlock {
-    type Error = PlaSubBlockConversionError;
+impl TryFrom<(u32, &PlaBlockNode)> for PlaDurationBlock {
+    type Error = PlaParseError;
 
-    fn try_from(value: &Box<dyn PlaSubBlock>) -> Result<Self, Self::Error> {
-        if value.get_command() != PlaCommand::DURATION {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::DURATION
-            });
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (parent_id, node) = value;
+        if node.arguments.is_empty() {
+            return Err(node.missing("unable to parse duration length for duration command"));
         }
 
-        let converted_opt: Option<&PlaDurationBlock> = value.downcast_ref::<PlaDurationBlock>();
-
-        if converted_opt.is_none() {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::DURATION
-            });
+        let mut duration_length = Duration::zero();
+        for token in &node.arguments {
+            let length = match parse_length(&token.text) {
+                Some(length) => length,
+                None => return Err(token.error("unable to parse duration, expected a number of hours or a length such as `14d`, `90m` or `1w 2d`"))
+            };
+
+            // Longer durations can't be added to any date, however early the entry starts
+            let longest = MAX_DATE.and_hms(23, 59, 59) - MIN_DATE.and_hms(0, 0, 0);
+            duration_length = match duration_length.checked_add(&length).filter(|total| *total <= longest) {
+                Some(total) => total,
+                None => return Err(token.error("duration is too long"))
+            };
         }
 
-        let dc_ref = converted_opt.unwrap();
         Ok(PlaDurationBlock {
-            parent_id: dc_ref.parent_id,
-            duration: dc_ref.duration
+            parent_id,
+            line_number: node.span.start.line_number,
+            duration: duration_length
         })
     }
 }
 
-impl PlaSubBlock for PlaDurationBlock {
-    fn get_command(&self) -> PlaCommand {
-        PlaCommand::DURATION
-    }
+try_from_str!{PlaDurationBlock}
 
-    fn get_parent_id(&self) -> u32 {
-        self.parent_id
-    }
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaChildBlock {
+    pub parent_id: u32,
+    pub line_number: usize,
+    pub child_id: u32
 }
 
-impl TryFrom<&HeirarchicalPlaLine> for PlaDurationBlock {
-    type Error = PlaParseError;
-
-    fn try_from(value: &HeirarchicalPlaLine) -> Result<Self, Self::Error> {
-        let str_command = value.text.trim_start();
-        match value.parent_id {
-            Some(x) => {
-                match PlaDurationBlock::try_from((x, str_command)) {
-                    Ok(x) => Ok(x),
-                    Err(_) => Err(PlaParseError{ message: String::from("duration block parsing failed")}),
-                }
-            },
-            None => Err(PlaParseError{ message: String::from("Unable to parse HeirarchicalPlaLine without parent id as PlaDurationBlock") }),
-        }
+impl Display for PlaChildBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", PlaCommand::CHILD, self.child_id)
     }
 }
 
-impl TryFrom<(u32, &str)> for PlaDurationBlock {
+impl TryFrom<(u32, &PlaBlockNode)> for PlaChildBlock {
     type Error = PlaParseError;
 
-    fn try_from(value: (u32, &str)) -> Result<Self, Self::Error> {
-        let (parent_id, input) = value;
-        let tokens: Vec<String> = input.split(' ').map(|s| String::from(s)).collect();
-
-        let duration_length: u32 = match tokens.get(1) {
-            Some(x) => match String::from(x).parse::<u32>() {
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (parent_id, node) = value;
+        let child_id: u32 = match node.arguments.first() {
+            Some(x) => match x.text.parse::<u32>() {
                 Ok(y) => y,
-                Err(_) => return Err(PlaParseError { message: String::from("Cannot parse duration for duration command as u32")} )
+                Err(_) => return Err(x.error("Cannot parse child id for child command as u32"))
             },
-            None => return Err(PlaParseError { message: String::from("unable to parse duration length for duration command") })
-
+            None => return Err(node.missing("unable to parse child id for child command")),
         };
 
-        Ok(PlaDurationBlock {
+        Ok(PlaChildBlock {
             parent_id,
-            duration: duration_length
+            line_number: node.span.start.line_number,
+            child_id
         })
     }
 }
 
+try_from_str!{PlaChildBlock}
+
 #[derive(Clone, Copy, Debug)]
-pub struct PlaChildBlock {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaProgressBlock {
     pub parent_id: u32,
-    pub child_id: u32
-}
+    pub line_number: usize,
 
-impl PlaSubBlock for PlaChildBlock {
-    fn get_command(&self) -> PlaCommand {
-        PlaCommand::CHILD
-    }
+    /// Percentage of the entry that is complete, from 0 to 100.
+    pub progress: u32
+}
 
-    fn get_parent_id(&self) -> u32 {
-        self.parent_id
+impl Display for PlaProgressBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", PlaCommand::PROGRESS, self.progress)
     }
 }
 
-try_from_box!{PlaChildBlock}
-
-impl TryFrom<&Box<dyn PlaSubBlock>> for PlaChildBlock {
-    type Error = PlaSubBlockConversionError;
-
-    fn try_from(value: &Box<dyn PlaSubBlock>) -> Result<Self, Self::Error> {
-        if value.get_command() != PlaCommand::CHILD {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::CHILD
-            });
-        }
-
-        let converted_opt: Option<&PlaChildBlock> = value.downcast_ref::<PlaChildBlock>();
+impl TryFrom<(u32, &PlaBlockNode)> for PlaProgressBlock {
+    type Error = PlaParseError;
 
-        if converted_opt.is_none() {
-            return Err(PlaSubBlockConversionError {
-                initial_type: PlaCommand::CHILD
-            });
-        }
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (parent_id, node) = value;
+        let progress: u32 = match node.arguments.first() {
+            Some(x) => match x.text.parse::<u32>() {
+                Ok(y) if y <= 100 => y,
+                Ok(_) => return Err(x.error("progress must be a percentage from 0 to 100")),
+                Err(_) => return Err(x.error("Cannot parse progress for progress command as u32"))
+            },
+            None => return Err(node.missing("unable to parse progress for progress command")),
+        };
 
-        let dc_ref = converted_opt.unwrap();
-        Ok(PlaChildBlock {
-            parent_id: dc_ref.parent_id,
-            child_id: dc_ref.child_id,
+        Ok(PlaProgressBlock {
+            parent_id,
+            line_number: node.span.start.line_number,
+            progress
         })
     }
 }
 
-impl TryFrom<&HeirarchicalPlaLine> for PlaChildBlock {
-    type Error = PlaParseError;
+try_from_str!{PlaProgressBlock}
+
+/// Colours that may be given by name, which are those that CSS understands.
+const NAMED_COLORS: [&str; 148] = [
+    "aliceblue", "antiquewhite", "aqua", "aquamarine", "azure", "beige", "bisque", "black",
+    "blanchedalmond", "blue", "blueviolet", "brown", "burlywood", "cadetblue", "chartreuse",
+    "chocolate", "coral", "cornflowerblue", "cornsilk", "crimson", "cyan", "darkblue", "darkcyan",
+    "darkgoldenrod", "darkgray", "darkgreen", "darkgrey", "darkkhaki", "darkmagenta",
+    "darkolivegreen", "darkorange", "darkorchid", "darkred", "darksalmon", "darkseagreen",
+    "darkslateblue", "darkslategray", "darkslategrey", "darkturquoise", "darkviolet", "deeppink",
+    "deepskyblue", "dimgray", "dimgrey", "dodgerblue", "firebrick", "floralwhite", "forestgreen",
+    "fuchsia", "gainsboro", "ghostwhite", "gold", "goldenrod", "gray", "green", "greenyellow",
+    "grey", "honeydew", "hotpink", "indianred", "indigo", "ivory", "khaki", "lavender",
+    "lavenderblush", "lawngreen", "lemonchiffon", "lightblue", "lightcoral", "lightcyan",
+    "lightgoldenrodyellow", "lightgray", "lightgreen", "lightgrey", "lightpink", "lightsalmon",
+    "lightseagreen", "lightskyblue", "lightslategray", "lightslategrey", "lightsteelblue",
+    "lightyellow", "lime", "limegreen", "linen", "magenta", "maroon", "mediumaquamarine",
+    "mediumblue", "mediumorchid", "mediumpurple", "mediumseagreen", "mediumslateblue",
+    "mediumspringgreen", "mediumturquoise", "mediumvioletred", "midnightblue", "mintcream",
+    "mistyrose", "moccasin", "navajowhite", "navy", "oldlace", "olive", "olivedrab", "orange",
+    "orangered", "orchid", "palegoldenrod", "palegreen", "paleturquoise", "palevioletred",
+    "papayawhip", "peachpuff", "peru", "pink", "plum", "powderblue", "purple", "rebeccapurple",
+    "red", "rosybrown", "royalblue", "saddlebrown", "salmon", "sandybrown", "seagreen",
+    "seashell", "sienna", "silver", "skyblue", "slateblue", "slategray", "slategrey", "snow",
+    "springgreen", "steelblue", "tan", "teal", "thistle", "tomato", "turquoise", "violet",
+    "wheat", "white", "whitesmoke", "yellow", "yellowgreen",
+];
+
+/// The colour an entry's bar is drawn in, either a CSS colour name or `#rrggbb`.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlaColorBlock {
+    pub parent_id: u32,
+    pub line_number: usize,
 
-    fn try_from(value: &HeirarchicalPlaLine) -> Result<Self, Self::Error> {
-        let str_command = value.text.trim_start();
-        match value.parent_id {
-            Some(x) => {
-                match PlaChildBlock::try_from((x, str_command)) {
-                    Ok(x) => Ok(x),
-                    Err(_) => Err(PlaParseError{ message: String::from("child block parsing failed")}),
-                }
-            },
-            None => Err(PlaParseError{ message: String::from("Unable to parse HeirarchicalPlaLine without parent id as PlaChildBlock") }),
-        }
+    /// Lowercase colour name or `#rrggbb`, which can be used as is in CSS and SVG.
+    pub color: String
+}
+
+impl Display for PlaColorBlock {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} {}", PlaCommand::COLOR, self.color)
     }
 }
 
-impl TryFrom<(u32, &str)> for PlaChildBlock {
+impl TryFrom<(u32, &PlaBlockNode)> for PlaColorBlock {
     type Error = PlaParseError;
 
-    fn try_from(value: (u32, &str)) -> Result<Self, Self::Error> {
-        let (parent_id, input) = value;
-        let tokens: Vec<String> = input.split(' ').map(|s| String::from(s)).collect();
+    fn try_from(value: (u32, &PlaBlockNode)) -> Result<Self, Self::Error> {
+        let (parent_id, node) = value;
+        let color = match node.arguments.first() {
+            Some(x) => {
+                let color = x.text.to_lowercase();
+                let is_hex = color.len() == 7
+                    && color.starts_with('#')
+                    && color.chars().skip(1).all(|c| c.is_ascii_hexdigit());
 
-        let child_id: u32 = match tokens.get(1) {
-            Some(x) => match String::from(x).parse::<u32>() {
-                Ok(y) => y,
-                Err(_) => return Err(PlaParseError { message: String::from("Cannot parse child id for child command as u32")})
+                if !is_hex && !NAMED_COLORS.contains(&color.as_str()) {
+                    return Err(x.error("expected a colour name or `#rrggbb` for color command"));
+                }
+                color
             },
-            None => return Err(PlaParseError { message: String::from("unable to parse child id for child command") }),
+            None => return Err(node.missing("unable to parse colour for color command")),
         };
 
-        Ok(PlaChildBlock {
+        Ok(PlaColorBlock {
             parent_id,
-            child_id
+            line_number: node.span.start.line_number,
+            color
         })
     }
 }
 
+try_from_str!{PlaColorBlock}
+
 #[cfg(test)]
 mod tests {
-    use crate::pla::sub_blocks::{PlaChildBlock, PlaDurationBlock};
+    use chrono::{Duration, NaiveDate};
+    use crate::pla::sub_blocks::{PlaChildBlock, PlaColorBlock, PlaDependencyBlock, PlaDurationBlock, PlaProgressBlock, PlaResourceBlock, PlaStartBlock};
 
     #[test]
     #[should_panic]
@@ -551,8 +577,107 @@ mod tests {
     fn it_should_parse_a_duration_block_from_a_valid_string_and_parent_id() {
         let duration_block: PlaDurationBlock = PlaDurationBlock::try_from((86, "duration 22")).unwrap();
         assert_eq!(86, duration_block.parent_id);
-        assert_eq!(22, duration_block.duration);
+        assert_eq!(Duration::hours(22), duration_block.duration);
+    }
+
+    #[test]
+    fn it_should_parse_a_duration_block_with_units() {
+        let length = |text: &str| PlaDurationBlock::try_from((86, text)).unwrap().duration;
+        assert_eq!(Duration::days(14), length("duration 14d"));
+        assert_eq!(Duration::minutes(90), length("duration 90m"));
+        assert_eq!(Duration::days(9), length("duration 1w 2d"));
+        assert_eq!(Duration::hours(6), length("duration 6h "));
+
+        let error = PlaDurationBlock::try_from((86, "duration 1w 2y")).unwrap_err();
+        assert_eq!((13, "2y"), (error.column, error.text.as_str()));
+        assert!(PlaDurationBlock::try_from((86, "duration d")).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_a_duration_too_long_to_add_to_a_date() {
+        let error = PlaDurationBlock::try_from((86, "duration 4294967295w")).unwrap_err();
+        assert_eq!(("duration is too long", 10), (error.message.as_str(), error.column));
+
+        let error = PlaDurationBlock::try_from((86, "duration 100000000d 100000000d")).unwrap_err();
+        assert_eq!((21, "100000000d"), (error.column, error.text.as_str()));
+    }
+
+    #[test]
+    fn it_should_parse_a_color_block_from_a_name_or_hex_code() {
+        assert_eq!("steelblue", PlaColorBlock::try_from((86, "color SteelBlue")).unwrap().color);
+        assert_eq!("#a0c8ff", PlaColorBlock::try_from((86, "color #A0C8FF")).unwrap().color);
     }
 
+    #[test]
+    fn it_should_write_each_block_as_it_would_be_parsed() {
+        let lines = ["child 202", "color #a0c8ff", "dep 89", "duration 90m", "progress 40", "res Mash Tun 15G G10", "start 2020-10-20 06"];
+        let written = [
+            PlaChildBlock::try_from((86, lines[0])).unwrap().to_string(),
+            PlaColorBlock::try_from((86, lines[1])).unwrap().to_string(),
+            PlaDependencyBlock::try_from((86, lines[2])).unwrap().to_string(),
+            PlaDurationBlock::try_from((86, lines[3])).unwrap().to_string(),
+            PlaProgressBlock::try_from((86, lines[4])).unwrap().to_string(),
+            PlaResourceBlock::try_from((86, lines[5])).unwrap().to_string(),
+            PlaStartBlock::try_from((86, lines[6])).unwrap().to_string(),
+        ];
+
+        assert_eq!(lines.map(String::from), written);
+        assert_eq!("duration 336", PlaDurationBlock::try_from((86, "duration 2w")).unwrap().to_string());
+        assert_eq!("start 2021-01-08 00", PlaStartBlock::try_from((86, "start 2021-01-8")).unwrap().to_string());
+        assert_eq!("start 2021-01-08 09:30 Europe/London", PlaStartBlock::try_from((86, "start 2021-01-08 09:30 Europe/London")).unwrap().to_string());
+    }
+
+    #[test]
+    fn it_should_parse_a_start_block_with_minutes_and_a_time_zone() {
+        let start_block = PlaStartBlock::try_from((86, "start 2021-07-01 16:45 +02:00")).unwrap();
+        assert_eq!(NaiveDate::from_ymd(2021, 7, 1).and_hms(16, 45, 0), start_block.time);
+        assert_eq!(Some(NaiveDate::from_ymd(2021, 7, 1).and_hms(15, 45, 0)), start_block.get_time_in(&"Europe/London".parse().unwrap()));
+
+        let floating = PlaStartBlock::try_from((86, "start 2021-07-01 16")).unwrap();
+        assert_eq!(Some(floating.time), floating.get_time_in(&"America/Chicago".parse().unwrap()));
+
+        let error = PlaStartBlock::try_from((86, "start 2021-07-01 16:45 Cellar")).unwrap_err();
+        assert_eq!((24, "Cellar"), (error.column, error.text.as_str()));
+        assert!(PlaStartBlock::try_from((86, "start 2021-07-01 16:75")).is_err());
+        assert!(PlaStartBlock::try_from((86, "start 2021-03-28 01:30 Europe/London")).is_err());
+    }
+
+    #[test]
+    fn it_should_reject_an_hour_too_far_past_the_date() {
+        let error = PlaStartBlock::try_from((86, "start 2021-01-01 4294967295")).unwrap_err();
+        assert_eq!((18, "4294967295"), (error.column, error.text.as_str()));
+        assert_eq!(NaiveDate::from_ymd(2021, 1, 2).and_hms(12, 0, 0), PlaStartBlock::try_from((86, "start 2021-01-01 36")).unwrap().time);
+    }
+
+    #[test]
+    fn it_should_reject_a_zoned_start_at_the_limits_of_the_calendar() {
+        let error = PlaStartBlock::try_from((86, "start +262143-12-31 23:00 -05:00")).unwrap_err();
+        assert_eq!((21, "23:00"), (error.column, error.text.as_str()));
+        assert!(PlaStartBlock::try_from((86, "start -262144-01-01 01:00 +05:00")).is_err());
+        assert!(PlaStartBlock::try_from((86, "start +262143-12-31 23:00")).is_ok());
+
+        // Blocks that weren't parsed can still hold such times, but have no time in another zone
+        let mut start = PlaStartBlock::try_from((86, "start +262143-12-30 23:00 -05:00")).unwrap();
+        assert_eq!(None, start.get_time_in(&"+05:00".parse().unwrap()));
+        start.time = NaiveDate::from_ymd(-262144, 1, 1).and_hms(1, 0, 0);
+        assert_eq!(None, start.get_time_in(&"UTC".parse().unwrap()));
+    }
+
+    #[test]
+    fn it_should_only_accept_progress_as_a_percentage() {
+        assert_eq!(100, PlaProgressBlock::try_from((86, "progress 100")).unwrap().progress);
+
+        let error = PlaProgressBlock::try_from((86, "progress 140")).unwrap_err();
+        assert_eq!((10, "140"), (error.column, error.text.as_str()));
+        assert!(PlaProgressBlock::try_from((86, "progress -5")).is_err());
+    }
+
+    #[test]
+    fn it_should_point_at_an_unrecognized_color() {
+        let error = PlaColorBlock::try_from((86, "color #12345")).unwrap_err();
+        assert_eq!((7, "#12345"), (error.column, error.text.as_str()));
+        assert!(PlaColorBlock::try_from((86, "color blurple")).is_err());
+        assert!(PlaColorBlock::try_from((86, "color")).is_err());
+    }
 }
 
@@ -0,0 +1,371 @@
+use std::str::FromStr;
+use crate::pla::error::PlaParseError;
+use crate::pla::lexer::{tokenize, PlaSpan, PlaToken, PlaTokenKind};
+
+/// A word of a pla file, such as an entry id, a command or one of its arguments.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaWordNode {
+    pub text: String,
+    pub span: PlaSpan,
+}
+
+impl PlaWordNode {
+    /// An error with `message`, pointing at this word.
+    pub fn error(&self, message: &str) -> PlaParseError {
+        PlaParseError::new(message).at(self.span.start.line_number, self.span.start.column, &self.text)
+    }
+}
+
+/// A comment, either at the end of a line or making up the whole of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaCommentNode {
+    pub text: String,
+    pub span: PlaSpan,
+}
+
+/// The first line of an entry, `[id] description`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaHeaderNode {
+    /// Digits between the brackets.
+    pub id: PlaWordNode,
+    pub description: Option<PlaWordNode>,
+    pub comment: Option<PlaCommentNode>,
+
+    /// From the opening bracket to the end of the description, without the comment.
+    pub span: PlaSpan,
+}
+
+/// A command and its arguments, on a line of its own.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaBlockNode {
+    pub command: PlaWordNode,
+    pub arguments: Vec<PlaWordNode>,
+    pub comment: Option<PlaCommentNode>,
+
+    /// The block as written, from the command to the end of its last argument.
+    pub text: String,
+    pub span: PlaSpan,
+}
+
+impl PlaBlockNode {
+    /// The text from argument `index` to the end of the block, as written, for arguments such as
+    /// resource names that may contain whitespace.
+    pub fn rest(&self, index: usize) -> Option<PlaWordNode> {
+        let first = self.arguments.get(index)?;
+        let offset = first.span.start.column - self.span.start.column;
+
+        Some(PlaWordNode {
+            text: self.text.chars().skip(offset).collect(),
+            span: first.span.to(self.span),
+        })
+    }
+
+    /// An error with `message` for a missing argument, pointing just past the end of the block.
+    pub fn missing(&self, message: &str) -> PlaParseError {
+        PlaParseError::new(message).at(self.span.end.line_number, self.span.end.column + 1, "")
+    }
+}
+
+/// Parses a single block, such as `duration 6`, with columns counted from the start of `s`.
+impl FromStr for PlaBlockNode {
+    type Err = PlaParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s, 0);
+        match tokens.first().map(|t| t.kind) {
+            None | Some(PlaTokenKind::Comment) => Err(PlaParseError::new("expected a command")),
+            Some(_) => Ok(SyntaxParser::block(&tokens, s)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaItemNode {
+    Block(PlaBlockNode),
+    Comment(PlaCommentNode),
+}
+
+impl PlaItemNode {
+    pub fn span(&self) -> PlaSpan {
+        match self {
+            PlaItemNode::Block(b) => b.span,
+            PlaItemNode::Comment(c) => c.span,
+        }
+    }
+}
+
+/// An entry header and the lines that follow it, up to the next header.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct PlaEntryNode {
+    pub header: PlaHeaderNode,
+    pub items: Vec<PlaItemNode>,
+    pub span: PlaSpan,
+}
+
+/// A whole pla file. Blocks before the first entry don't belong to any entry, but are kept so
+/// that they can be reported.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct PlaFileNode {
+    pub preamble: Vec<PlaItemNode>,
+    pub entries: Vec<PlaEntryNode>,
+    pub span: PlaSpan,
+}
+
+/// Parse the lines of a pla file following the grammar in `docs/pla.bnf`. Entries whose header is
+/// malformed are skipped, along with their lines, and an error is returned for each of them.
+pub fn parse(lines: &[String]) -> (PlaFileNode, Vec<PlaParseError>) {
+    let mut parser = SyntaxParser {
+        lines: lines
+            .iter()
+            .enumerate()
+            .map(|(idx, l)| (tokenize(l, idx + 1), l.as_str()))
+            .filter(|(tokens, _)| !tokens.is_empty())
+            .collect(),
+        position: 0,
+        errors: vec![],
+    };
+
+    let file = parser.file();
+    (file, parser.errors)
+}
+
+/// Recursive descent parser over the tokens of each non-blank line.
+struct SyntaxParser<'a> {
+    lines: Vec<(Vec<PlaToken>, &'a str)>,
+    position: usize,
+    errors: Vec<PlaParseError>,
+}
+
+impl<'a> SyntaxParser<'a> {
+    /// pla_file ::= pla_items pla_entries
+    fn file(&mut self) -> PlaFileNode {
+        let preamble = self.items();
+        let mut entries: Vec<PlaEntryNode> = vec![];
+        while self.position < self.lines.len() {
+            match self.entry() {
+                Ok(entry) => entries.push(entry),
+                Err(e) => {
+                    self.errors.push(e);
+                    self.items();
+                },
+            }
+        }
+
+        let first = preamble.first().map(|i| i.span()).or_else(|| entries.first().map(|e| e.span));
+        let last = entries.last().map(|e| e.span).or_else(|| preamble.last().map(|i| i.span()));
+        let span = match (first, last) {
+            (Some(first), Some(last)) => first.to(last),
+            _ => PlaSpan::default(),
+        };
+
+        PlaFileNode { preamble, entries, span }
+    }
+
+    /// pla_entry ::= pla_header newline pla_items
+    fn entry(&mut self) -> Result<PlaEntryNode, PlaParseError> {
+        let (tokens, line) = &self.lines[self.position];
+        let header = SyntaxParser::header(tokens, line);
+        self.position += 1;
+
+        let header = header?;
+        let items = self.items();
+        let span = match items.last() {
+            Some(item) => header.span.to(item.span()),
+            None => header.span,
+        };
+
+        Ok(PlaEntryNode { header, items, span })
+    }
+
+    /// pla_items ::= "" | pla_item newline pla_items
+    fn items(&mut self) -> Vec<PlaItemNode> {
+        let mut items: Vec<PlaItemNode> = vec![];
+        while let Some((tokens, line)) = self.lines.get(self.position) {
+            let item = match tokens[0].kind {
+                PlaTokenKind::OpenBracket => break,
+                PlaTokenKind::Comment => PlaItemNode::Comment(SyntaxParser::comment(&tokens[0])),
+                _ => PlaItemNode::Block(SyntaxParser::block(tokens, line)),
+            };
+
+            items.push(item);
+            self.position += 1;
+        }
+
+        items
+    }
+
+    /// pla_header ::= "[" id "]" description comment
+    fn header(tokens: &[PlaToken], line: &str) -> Result<PlaHeaderNode, PlaParseError> {
+        let (tokens, comment) = SyntaxParser::split_comment(tokens);
+        let open = &tokens[0];
+        let malformed = || {
+            let span = open.span.to(tokens[tokens.len() - 1].span);
+            PlaParseError::new("expected an entry of the form `[id] description`")
+                .at(span.start.line_number, span.start.column, &SyntaxParser::source(line, span))
+        };
+
+        // A missing id is reported where it should have been.
+        let id = match tokens.get(1) {
+            Some(t) if t.kind == PlaTokenKind::CloseBracket => {
+                return Err(PlaParseError::new("unable to parse entry id as u32").at(t.span.start.line_number, t.span.start.column, ""));
+            },
+            Some(t) if t.kind == PlaTokenKind::Word && t.text.chars().all(|c| c.is_ascii_digit()) => {
+                PlaWordNode { text: String::from(&t.text), span: t.span }
+            },
+            _ => return Err(malformed()),
+        };
+
+        let close = match tokens.get(2) {
+            Some(t) if t.kind == PlaTokenKind::CloseBracket => t,
+            _ => return Err(malformed()),
+        };
+
+        let rest = &tokens[3..];
+        let description = match (rest.first(), rest.last()) {
+            (Some(first), Some(last)) => {
+                let span = first.span.to(last.span);
+                Some(PlaWordNode { text: SyntaxParser::source(line, span), span })
+            },
+            _ => None,
+        };
+
+        let span = match &description {
+            Some(d) => open.span.to(d.span),
+            None => open.span.to(close.span),
+        };
+
+        Ok(PlaHeaderNode { id, description, comment, span })
+    }
+
+    /// pla_block ::= command arguments comment
+    fn block(tokens: &[PlaToken], line: &str) -> PlaBlockNode {
+        let (tokens, comment) = SyntaxParser::split_comment(tokens);
+        let words: Vec<PlaWordNode> = tokens
+            .iter()
+            .map(|t| PlaWordNode { text: String::from(&t.text), span: t.span })
+            .collect();
+
+        let span = words[0].span.to(words[words.len() - 1].span);
+        PlaBlockNode {
+            command: words[0].clone(),
+            arguments: words[1..].to_vec(),
+            comment,
+            text: SyntaxParser::source(line, span),
+            span,
+        }
+    }
+
+    fn comment(token: &PlaToken) -> PlaCommentNode {
+        PlaCommentNode { text: String::from(&token.text), span: token.span }
+    }
+
+    /// The tokens of a line before its comment, and the comment, if it has one.
+    fn split_comment(tokens: &[PlaToken]) -> (&[PlaToken], Option<PlaCommentNode>) {
+        match tokens.split_last() {
+            Some((last, rest)) if last.kind == PlaTokenKind::Comment => (rest, Some(SyntaxParser::comment(last))),
+            _ => (tokens, None),
+        }
+    }
+
+    /// Text of `line` covered by `span`, as written.
+    fn source(line: &str, span: PlaSpan) -> String {
+        line.chars().skip(span.start.column - 1).take(span.end.column - span.start.column).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use crate::pla::lexer::PlaSpan;
+    use crate::pla::syntax::{parse, PlaBlockNode, PlaItemNode};
+
+    fn lines(text: &str) -> Vec<String> {
+        text.split('\n').map(String::from).collect()
+    }
+
+    #[test]
+    fn it_should_give_every_node_a_span() {
+        let (file, errors) = parse(&lines(r#"
+[89]  Brewing	Process # first batch
+	start  2020-10-20	16
+    // clean the tun afterwards
+"#));
+
+        assert!(errors.is_empty());
+        assert!(file.preamble.is_empty());
+        assert_eq!(PlaSpan::new(2, 1, 0).to(PlaSpan::new(4, 5, 27)), file.span);
+
+        let entry = &file.entries[0];
+        assert_eq!(file.span, entry.span);
+        assert_eq!("89", entry.header.id.text);
+        assert_eq!(PlaSpan::new(2, 2, 2), entry.header.id.span);
+        assert_eq!("Brewing\tProcess", entry.header.description.as_ref().unwrap().text);
+        assert_eq!(PlaSpan::new(2, 7, 15), entry.header.description.as_ref().unwrap().span);
+        assert_eq!(PlaSpan::new(2, 23, 13), entry.header.comment.as_ref().unwrap().span);
+
+        match &entry.items[..] {
+            [PlaItemNode::Block(start), PlaItemNode::Comment(comment)] => {
+                assert_eq!("start", start.command.text);
+                assert_eq!(PlaSpan::new(3, 2, 20), start.span);
+                assert_eq!(PlaSpan::new(3, 9, 10), start.arguments[0].span);
+                assert_eq!(PlaSpan::new(3, 20, 2), start.arguments[1].span);
+                assert_eq!("clean the tun afterwards", comment.text);
+            },
+            items => panic!("Expected a block and a comment, got {:?}", items),
+        }
+    }
+
+    #[test]
+    fn it_should_keep_the_text_of_the_rest_of_a_block() {
+        let block: PlaBlockNode = "res  Mash Tun\t15G # the big one".parse().unwrap();
+        assert_eq!("res  Mash Tun\t15G", block.text);
+        assert_eq!("Mash Tun\t15G", block.rest(0).unwrap().text);
+        assert_eq!(PlaSpan::new(0, 6, 12), block.rest(0).unwrap().span);
+        assert!(block.rest(3).is_none());
+        assert_eq!(19, block.missing("").column);
+    }
+
+    #[test]
+    fn it_should_skip_an_entry_with_a_malformed_header() {
+        let (file, errors) = parse(&lines("duration 6\n[x90] Transfer\n    duration 2\n[] Keg\n[91] Kegging\n    duration 3"));
+
+        assert_eq!(1, file.preamble.len());
+        assert_eq!(1, file.entries.len());
+        assert_eq!("91", file.entries[0].header.id.text);
+
+        let messages: Vec<(usize, usize, &str)> = errors.iter().map(|e| (e.line_number, e.column, e.message.as_str())).collect();
+        assert_eq!(vec![
+            (2, 1, "expected an entry of the form `[id] description`"),
+            (4, 2, "unable to parse entry id as u32"),
+        ], messages);
+    }
+
+    /// Every line of the fixtures should end up in the tree, with the same command a naive split
+    /// of the line would find.
+    #[test]
+    fn it_should_parse_every_line_of_the_contrib_fixtures() {
+        for name in ["contrib/pla_simple.pla", "contrib/pla_complicated.pla"] {
+            let text = std::fs::read_to_string(PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(name)).unwrap();
+            let (file, errors) = parse(&lines(&text));
+            assert!(errors.is_empty(), "{}: {:?}", name, errors);
+            assert!(file.preamble.is_empty());
+
+            let mut expected: HashMap<String, usize> = HashMap::new();
+            for line in text.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('[')) {
+                *expected.entry(String::from(line.split_whitespace().next().unwrap())).or_default() += 1;
+            }
+
+            let mut actual: HashMap<String, usize> = HashMap::new();
+            for item in file.entries.iter().flat_map(|e| e.items.iter()) {
+                match item {
+                    PlaItemNode::Block(b) => *actual.entry(String::from(&b.command.text)).or_default() += 1,
+                    PlaItemNode::Comment(c) => panic!("{}: unexpected comment {:?}", name, c),
+                }
+            }
+
+            assert_eq!(expected, actual, "{}", name);
+            assert_eq!(text.lines().filter(|l| l.starts_with('[')).count(), file.entries.len(), "{}", name);
+        }
+    }
+}
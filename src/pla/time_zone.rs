@@ -0,0 +1,174 @@
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+use chrono::{Duration, FixedOffset, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use crate::pla::error::PlaParseError;
+
+/// Time zone that a start time is written in, or that a chart is drawn in. Either a fixed offset
+/// from UTC, e.g. `+02:00`, or a named zone, e.g. `Europe/London`, whose offset changes with
+/// daylight saving time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PlaTimeZone {
+    Offset(FixedOffset),
+    Named(Tz),
+}
+
+impl PlaTimeZone {
+    pub const UTC: PlaTimeZone = PlaTimeZone::Named(Tz::UTC);
+
+    /// The moment, in UTC, at which clocks in this zone read `time`. Times that happen twice,
+    /// when clocks go back, are the earlier of the two. `None` if clocks in this zone skip `time`,
+    /// or if it can't be converted, see [`PlaTimeZone::can_convert`].
+    pub fn to_utc(&self, time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        if !PlaTimeZone::can_convert(time) {
+            return None;
+        }
+
+        match self {
+            PlaTimeZone::Offset(offset) => time.checked_sub_signed(Duration::seconds(offset.local_minus_utc() as i64)),
+            PlaTimeZone::Named(tz) => tz.from_local_datetime(time).earliest().map(|t| t.naive_utc()),
+        }
+    }
+
+    /// What clocks in this zone read at the moment `time`, in UTC. `None` if it can't be
+    /// converted, see [`PlaTimeZone::can_convert`].
+    pub fn from_utc(&self, time: &NaiveDateTime) -> Option<NaiveDateTime> {
+        if !PlaTimeZone::can_convert(time) {
+            return None;
+        }
+
+        match self {
+            PlaTimeZone::Offset(offset) => time.checked_add_signed(Duration::seconds(offset.local_minus_utc() as i64)),
+            PlaTimeZone::Named(tz) => Some(tz.from_utc_datetime(time).naive_local()),
+        }
+    }
+
+    /// Whether `time` is far enough from the first and last times chrono can represent to be
+    /// converted between zones. No zone is a day or more from UTC, so every time at least a day
+    /// from either end can be, whatever the zone.
+    pub fn can_convert(time: &NaiveDateTime) -> bool {
+        time.checked_sub_signed(Duration::days(1)).is_some() && time.checked_add_signed(Duration::days(1)).is_some()
+    }
+}
+
+impl Display for PlaTimeZone {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlaTimeZone::Offset(offset) => write!(f, "{}", offset),
+            PlaTimeZone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+impl FromStr for PlaTimeZone {
+    type Err = PlaParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "Z" {
+            return Ok(PlaTimeZone::Offset(FixedOffset::east(0)));
+        }
+
+        if let Some((west, hours, minutes)) = parse_offset(s) {
+            let seconds = (hours * 60 + minutes) * 60;
+            let seconds = if west { -seconds } else { seconds };
+
+            return match FixedOffset::east_opt(seconds) {
+                Some(offset) if hours < 24 && minutes < 60 => Ok(PlaTimeZone::Offset(offset)),
+                _ => Err(PlaParseError::new("UTC offset is out of range")),
+            };
+        }
+
+        match s.parse::<Tz>() {
+            Ok(tz) => Ok(PlaTimeZone::Named(tz)),
+            Err(_) => Err(PlaParseError::new("expected a UTC offset such as `+02:00` or a time zone such as `Europe/London`")),
+        }
+    }
+}
+
+/// Whether an offset is west of UTC, and its hours and minutes. Offsets are written as in
+/// ISO 8601: `+02:00`, `+0200` or `+02`.
+fn parse_offset(s: &str) -> Option<(bool, i32, i32)> {
+    let (west, rest) = match s.chars().next()? {
+        '+' => (false, &s[1..]),
+        '-' => (true, &s[1..]),
+        _ => return None,
+    };
+
+    let two_digits = |t: &str| -> Option<i32> {
+        if t.len() == 2 && t.bytes().all(|b| b.is_ascii_digit()) { t.parse().ok() } else { None }
+    };
+
+    let hours = two_digits(rest.get(..2)?)?;
+    let rest = &rest[2..];
+    let rest = rest.strip_prefix(':').unwrap_or(rest);
+    let minutes = if rest.is_empty() { 0 } else { two_digits(rest)? };
+
+    Some((west, hours, minutes))
+}
+
+/// Written as it would be in a `start` block, e.g. `+02:00` or `Europe/London`.
+#[cfg(feature = "serde")]
+impl serde::Serialize for PlaTimeZone {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PlaTimeZone {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = String::deserialize(deserializer)?;
+        s.parse().map_err(|e: PlaParseError| serde::de::Error::custom(e.message))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{FixedOffset, NaiveDate};
+    use chrono_tz::Tz;
+    use crate::pla::time_zone::PlaTimeZone;
+
+    #[test]
+    fn it_should_parse_offsets_and_named_zones() {
+        let two_hours_east = PlaTimeZone::Offset(FixedOffset::east(2 * 3600));
+        assert_eq!(Ok(two_hours_east), "+02:00".parse());
+        assert_eq!(Ok(two_hours_east), "+0200".parse());
+        assert_eq!(Ok(PlaTimeZone::Offset(FixedOffset::west(5 * 3600 + 1800))), "-05:30".parse());
+        assert_eq!(Ok(PlaTimeZone::Named(Tz::America__Chicago)), "America/Chicago".parse());
+        assert_eq!(Ok(PlaTimeZone::UTC), "UTC".parse());
+        assert!("Cellar/Basement".parse::<PlaTimeZone>().is_err());
+        assert!("+25:00".parse::<PlaTimeZone>().is_err());
+        assert_eq!(Ok(two_hours_east), "+02".parse());
+        assert!("+2:00".parse::<PlaTimeZone>().is_err());
+        assert!("+02:0".parse::<PlaTimeZone>().is_err());
+        assert!("+02:00:00".parse::<PlaTimeZone>().is_err());
+    }
+
+    #[test]
+    fn it_should_not_convert_times_at_the_limits_of_the_calendar() {
+        let west: PlaTimeZone = "-05:00".parse().unwrap();
+        let last = NaiveDate::from_ymd(262143, 12, 31).and_hms(23, 0, 0);
+        let first = NaiveDate::from_ymd(-262144, 1, 1).and_hms(1, 0, 0);
+
+        assert_eq!(None, west.to_utc(&last));
+        assert_eq!(None, west.from_utc(&first));
+        assert_eq!(None, PlaTimeZone::UTC.to_utc(&first));
+        assert_eq!(None, "Europe/London".parse::<PlaTimeZone>().unwrap().from_utc(&last));
+        assert!(!PlaTimeZone::can_convert(&last));
+        assert!(PlaTimeZone::can_convert(&NaiveDate::from_ymd(262143, 12, 30).and_hms(23, 0, 0)));
+    }
+
+    #[test]
+    fn it_should_convert_wall_clock_times_through_utc() {
+        let london: PlaTimeZone = "Europe/London".parse().unwrap();
+        let summer = NaiveDate::from_ymd(2021, 7, 1).and_hms(9, 30, 0);
+        let winter = NaiveDate::from_ymd(2021, 12, 1).and_hms(9, 30, 0);
+
+        assert_eq!(Some(NaiveDate::from_ymd(2021, 7, 1).and_hms(8, 30, 0)), london.to_utc(&summer));
+        assert_eq!(Some(winter), london.to_utc(&winter));
+        assert_eq!(Some(summer), london.from_utc(&london.to_utc(&summer).unwrap()));
+
+        // Clocks in London jump from 01:00 to 02:00 at the end of March.
+        assert_eq!(None, london.to_utc(&NaiveDate::from_ymd(2021, 3, 28).and_hms(1, 30, 0)));
+    }
+}
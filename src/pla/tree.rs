@@ -0,0 +1,241 @@
+use std::collections::{HashMap, HashSet};
+use chrono::Duration;
+use crate::pla::entry::PlaEntry;
+use crate::pla::error::PlaTreeError;
+
+/// An entry along with the entries referenced by its `child` blocks.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaTreeNode {
+    pub entry: PlaEntry,
+    pub children: Vec<PlaTreeNode>,
+}
+
+impl PlaTreeNode {
+    pub fn has_children(&self) -> bool {
+        !self.children.is_empty()
+    }
+
+    /// Percentage of this node that is complete. Leaves use their `progress` block, while parents
+    /// average their children weighted by how long each takes, counting children without progress
+    /// as not started. `None` if nothing beneath the node records any progress.
+    pub fn get_progress(&self) -> Option<f64> {
+        if !self.has_children() {
+            return self.entry.get_progress().map(f64::from);
+        }
+
+        let progress: Vec<Option<f64>> = self.children.iter().map(|c| c.get_progress()).collect();
+        if progress.iter().all(Option::is_none) {
+            return None;
+        }
+
+        let work: Vec<f64> = self.children.iter().map(|c| c.get_work().num_minutes() as f64).collect();
+        let total_work: f64 = work.iter().sum();
+        let done = progress.iter().map(|p| p.unwrap_or(0.0));
+
+        // Children that take no time at all, e.g. milestones, count equally.
+        if total_work == 0.0 {
+            return Some(done.sum::<f64>() / self.children.len() as f64);
+        }
+
+        Some(done.zip(work.iter()).map(|(p, w)| p * w).sum::<f64>() / total_work)
+    }
+
+    /// Total length of the leaves beneath this node, or of the node itself if it is a leaf.
+    fn get_work(&self) -> Duration {
+        if !self.has_children() {
+            return self.entry.get_length();
+        }
+
+        self.children.iter().fold(Duration::zero(), |total, c| total + c.get_work())
+    }
+}
+
+/// Entries of a pla file arranged by their `child` blocks. Entries that are not the child of any
+/// other entry become roots, in the order they appear in the file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlaTree {
+    pub roots: Vec<PlaTreeNode>,
+}
+
+impl PlaTree {
+    pub fn new(entries: &[PlaEntry]) -> Result<PlaTree, PlaTreeError> {
+        let entry_map: HashMap<u32, &PlaEntry> = entries.iter().map(|e| (e.id, e)).collect();
+
+        let mut child_map: HashMap<u32, Vec<u32>> = HashMap::new();
+        for entry in entries {
+            let child_ids: Vec<u32> = entry
+                .children()
+                .iter()
+                .map(|c| c.child_id)
+                .collect();
+
+            for child_id in &child_ids {
+                if !entry_map.contains_key(child_id) {
+                    return Err(PlaTreeError::MissingChild { parent_id: entry.id, child_id: *child_id });
+                }
+            }
+
+            child_map.insert(entry.id, child_ids);
+        }
+
+        let mut finished: HashSet<u32> = HashSet::new();
+        for entry in entries {
+            let mut path: Vec<u32> = vec![];
+            PlaTree::check_cycles(entry.id, &child_map, &mut path, &mut finished)?;
+        }
+
+        let all_children: HashSet<u32> = child_map.values().flatten().copied().collect();
+        let roots: Vec<PlaTreeNode> = entries
+            .iter()
+            .filter(|e| !all_children.contains(&e.id))
+            .map(|e| PlaTree::build_node(e.id, &entry_map, &child_map))
+            .collect();
+
+        Ok(PlaTree {
+            roots
+        })
+    }
+
+    /// All nodes of the tree in depth-first order, paired with their depth (roots are at depth 0).
+    pub fn flatten(&self) -> Vec<(usize, &PlaTreeNode)> {
+        let mut nodes: Vec<(usize, &PlaTreeNode)> = vec![];
+        for root in &self.roots {
+            PlaTree::flatten_node(root, 0, &mut nodes);
+        }
+
+        nodes
+    }
+
+    fn flatten_node<'a>(node: &'a PlaTreeNode, depth: usize, nodes: &mut Vec<(usize, &'a PlaTreeNode)>) {
+        nodes.push((depth, node));
+        for child in &node.children {
+            PlaTree::flatten_node(child, depth + 1, nodes);
+        }
+    }
+
+    fn check_cycles(id: u32, child_map: &HashMap<u32, Vec<u32>>, path: &mut Vec<u32>,
+                    finished: &mut HashSet<u32>) -> Result<(), PlaTreeError> {
+        if finished.contains(&id) {
+            return Ok(());
+        }
+
+        if let Some(pos) = path.iter().position(|p| *p == id) {
+            let mut cycle: Vec<u32> = path[pos..].to_vec();
+            cycle.push(id);
+            return Err(PlaTreeError::Cycle { path: cycle });
+        }
+
+        path.push(id);
+        if let Some(child_ids) = child_map.get(&id) {
+            for child_id in child_ids {
+                PlaTree::check_cycles(*child_id, child_map, path, finished)?;
+            }
+        }
+        path.pop();
+        finished.insert(id);
+
+        Ok(())
+    }
+
+    fn build_node(id: u32, entry_map: &HashMap<u32, &PlaEntry>, child_map: &HashMap<u32, Vec<u32>>) -> PlaTreeNode {
+        let children: Vec<PlaTreeNode> = child_map
+            .get(&id)
+            .map_or(vec![], |child_ids| {
+                child_ids
+                    .iter()
+                    .map(|child_id| PlaTree::build_node(*child_id, entry_map, child_map))
+                    .collect()
+            });
+
+        PlaTreeNode {
+            entry: entry_map[&id].clone(),
+            children,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use crate::pla::error::PlaTreeError;
+    use crate::pla::parser::PlaParser;
+    use crate::pla::fixtures::get_parent;
+    use crate::pla::tree::PlaTree;
+
+    #[test]
+    fn it_should_build_a_tree_from_child_blocks() {
+        let entries = vec![
+            get_parent(10000, "Entry 10000", vec![89, 90]),
+            get_parent(89, "Entry 89", vec![]),
+            get_parent(90, "Entry 90", vec![91]),
+            get_parent(91, "Entry 91", vec![]),
+            get_parent(126, "Entry 126", vec![]),
+        ];
+
+        let tree = PlaTree::new(&entries).unwrap();
+        let flattened: Vec<(usize, u32)> = tree.flatten().iter().map(|(d, n)| (*d, n.entry.id)).collect();
+        assert_eq!(vec![(0, 10000), (1, 89), (1, 90), (2, 91), (0, 126)], flattened);
+    }
+
+    #[test]
+    fn it_should_report_a_missing_child() {
+        let entries = vec![get_parent(10000, "Entry 10000", vec![89])];
+        assert_eq!(Err(PlaTreeError::MissingChild { parent_id: 10000, child_id: 89 }), PlaTree::new(&entries));
+    }
+
+    #[test]
+    fn it_should_report_a_cycle() {
+        let entries = vec![
+            get_parent(1, "Entry 1", vec![2]),
+            get_parent(2, "Entry 2", vec![3]),
+            get_parent(3, "Entry 3", vec![1]),
+        ];
+
+        assert_eq!(Err(PlaTreeError::Cycle { path: vec![1, 2, 3, 1] }), PlaTree::new(&entries));
+    }
+
+    #[test]
+    fn it_should_build_a_tree_from_a_simple_pla_file() {
+        let dir = env!("CARGO_MANIFEST_DIR");
+        let mut path_buf: PathBuf = PathBuf::new();
+        path_buf.push(dir);
+        path_buf.push("contrib");
+        path_buf.push("pla_simple.pla");
+
+        let pla_parser = PlaParser::new(path_buf.as_path()).unwrap();
+        let tree = pla_parser.build_tree().unwrap();
+        let batch = tree.roots.iter().find(|n| n.entry.id == 10000).unwrap();
+
+        assert!(batch.has_children());
+        assert_eq!(122, batch.children[0].entry.id);
+    }
+
+    #[test]
+    fn it_should_roll_up_progress_weighted_by_duration() {
+        let pla_parser: PlaParser = r#"[10000] Autumn's Early Arrival Blonde
+    child 1
+    child 4
+
+[1] Brew Day
+    child 2
+    child 3
+
+[2] Brewing Process
+    duration 6
+    progress 100
+
+[3] Brewing Clean
+    duration 2
+
+[4] Primary Fermentation Process
+    duration 24
+    progress 25
+"#.parse().unwrap();
+
+        let tree = PlaTree::new(&pla_parser.entries).unwrap();
+        let root = &tree.roots[0];
+        assert_eq!(Some(75.0), root.children[0].get_progress());
+        assert_eq!(Some(37.5), root.get_progress());
+        assert_eq!(None, root.children[0].children[1].get_progress());
+    }
+}
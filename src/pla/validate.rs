@@ -0,0 +1,283 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::fmt::{Display, Formatter};
+use chrono::NaiveDateTime;
+use crate::pla::entry::PlaEntry;
+use crate::pla::error::{PlaScheduleError, PlaTreeError};
+use crate::pla::schedule::{PlaSchedule, PlaScheduledTime};
+
+/// A problem found while validating the entries of a pla file. Line numbers are 1-based lines
+/// of the offending block within the source file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PlaViolation {
+    /// An entry starts before one of the entries it depends on has ended.
+    DependencyNotFinished {
+        entry_id: u32,
+        dependency_id: u32,
+        line_number: usize,
+        start: NaiveDateTime,
+        dependency_end: NaiveDateTime,
+    },
+
+    /// A `dep` block refers to an id that has no matching entry.
+    DanglingDependency { entry_id: u32, dependency_id: u32, line_number: usize },
+
+    /// A `child` block refers to an id that has no matching entry.
+    DanglingChild { entry_id: u32, child_id: u32, line_number: usize },
+
+    /// Entries depend on themselves through `child` or `dep` blocks, so they can't be scheduled.
+    Cycle { path: Vec<u32> },
+
+    /// An entry would start or end outside the times that can be represented, so it can't be
+    /// scheduled. The line is that of its `duration` block, or failing that its `start` block or
+    /// header.
+    OutOfRange { entry_id: u32, line_number: usize },
+}
+
+impl PlaViolation {
+    /// Line of the block that caused this violation, if it can be attributed to a single line.
+    pub fn get_line_number(&self) -> Option<usize> {
+        match self {
+            PlaViolation::DependencyNotFinished { line_number, .. } => Some(*line_number),
+            PlaViolation::DanglingDependency { line_number, .. } => Some(*line_number),
+            PlaViolation::DanglingChild { line_number, .. } => Some(*line_number),
+            PlaViolation::Cycle { .. } => None,
+            PlaViolation::OutOfRange { line_number, .. } => Some(*line_number),
+        }
+    }
+
+    /// Description of the violation, without the line it occurred on.
+    pub fn get_message(&self) -> String {
+        match self {
+            PlaViolation::DependencyNotFinished { entry_id, dependency_id, start, dependency_end, .. } => {
+                format!("entry {} starts at {} before dependency {} ends at {}", entry_id,
+                        start.format("%Y-%m-%d %H:%M"), dependency_id, dependency_end.format("%Y-%m-%d %H:%M"))
+            },
+            PlaViolation::DanglingDependency { entry_id, dependency_id, .. } => {
+                format!("entry {} depends on {}, which does not exist", entry_id, dependency_id)
+            },
+            PlaViolation::DanglingChild { entry_id, child_id, .. } => {
+                format!("entry {} lists child {}, which does not exist", entry_id, child_id)
+            },
+            PlaViolation::Cycle { path } => {
+                let path_str: Vec<String> = path.iter().map(|id| id.to_string()).collect();
+                format!("entries depend on themselves: {}", path_str.join(" -> "))
+            },
+            PlaViolation::OutOfRange { entry_id, .. } => {
+                format!("entry {} starts or ends outside the dates that can be scheduled", entry_id)
+            },
+        }
+    }
+}
+
+impl Display for PlaViolation {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self.get_line_number() {
+            Some(line_number) => write!(f, "line {}: {}", line_number, self.get_message()),
+            None => write!(f, "{}", self.get_message()),
+        }
+    }
+}
+
+/// Check the entries of a pla file for dangling `dep` and `child` ids, cycles, and entries that
+/// start before their dependencies have ended. Violations are sorted by line number, with
+/// cycles, which span several lines, last.
+pub fn validate(entries: &[PlaEntry]) -> Vec<PlaViolation> {
+    let ids: HashSet<u32> = entries.iter().map(|e| e.id).collect();
+    let mut violations: Vec<PlaViolation> = vec![];
+
+    for entry in entries {
+        for child in entry.children() {
+            if !ids.contains(&child.child_id) {
+                violations.push(PlaViolation::DanglingChild {
+                    entry_id: entry.id,
+                    child_id: child.child_id,
+                    line_number: child.line_number,
+                });
+            }
+        }
+
+        for dependency in entry.dependencies() {
+            if !ids.contains(&dependency.dependency_id) {
+                violations.push(PlaViolation::DanglingDependency {
+                    entry_id: entry.id,
+                    dependency_id: dependency.dependency_id,
+                    line_number: dependency.line_number,
+                });
+            }
+        }
+    }
+
+    // If the file can't be scheduled as a whole, fall back to the times written in the file so
+    // that explicit mistakes are still reported.
+    let schedule = match PlaSchedule::new(entries) {
+        Ok(s) => Some(s),
+        Err(PlaScheduleError::Hierarchy(PlaTreeError::MissingChild { .. })) => None,
+        Err(PlaScheduleError::Hierarchy(PlaTreeError::Cycle { path }))
+        | Err(PlaScheduleError::DependencyCycle { path }) => {
+            violations.push(PlaViolation::Cycle { path });
+            None
+        },
+        Err(PlaScheduleError::OutOfRange { id }) => {
+            let line_number = entries.iter().find(|e| e.id == id).map_or(0, |e| {
+                e.duration().map(|d| d.line_number)
+                    .or_else(|| e.start().map(|s| s.line_number))
+                    .unwrap_or(e.line_number)
+            });
+            violations.push(PlaViolation::OutOfRange { entry_id: id, line_number });
+            None
+        },
+    };
+
+    let get_time = |entry: &PlaEntry| -> Option<PlaScheduledTime> {
+        match &schedule {
+            Some(s) => s.get(entry.id),
+            None => match (entry.get_start_time(), entry.get_end_time()) {
+                (Some(start), Some(end)) => Some(PlaScheduledTime { start, end }),
+                _ => None,
+            },
+        }
+    };
+
+    for entry in entries {
+        let start = match get_time(entry) {
+            Some(t) => t.start,
+            None => continue,
+        };
+
+        for dependency in entry.dependencies() {
+            let dependency_time = entries
+                .iter()
+                .find(|e| e.id == dependency.dependency_id)
+                .and_then(get_time);
+
+            if let Some(dependency_time) = dependency_time {
+                if start < dependency_time.end {
+                    violations.push(PlaViolation::DependencyNotFinished {
+                        entry_id: entry.id,
+                        dependency_id: dependency.dependency_id,
+                        line_number: dependency.line_number,
+                        start,
+                        dependency_end: dependency_time.end,
+                    });
+                }
+            }
+        }
+    }
+
+    violations.sort_by_key(|v| v.get_line_number().unwrap_or(usize::MAX));
+    violations
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+    use chrono::NaiveDate;
+    use chrono::naive::MAX_DATE;
+    use crate::pla::fixtures::get_entry;
+    use crate::pla::parser::PlaParser;
+    use crate::pla::sub_blocks::{PlaStartBlock, PlaSubBlock};
+    use crate::pla::time_zone::PlaTimeZone;
+    use crate::pla::validate::{validate, PlaViolation};
+
+    fn parse(text: &str) -> PlaParser {
+        PlaParser::parse(text.split('\n').map(String::from).collect()).unwrap()
+    }
+
+    #[test]
+    fn it_should_not_report_violations_for_the_contrib_files() {
+        for file_name in ["pla_simple.pla", "pla_complicated.pla"] {
+            let dir = env!("CARGO_MANIFEST_DIR");
+            let mut path_buf: PathBuf = PathBuf::new();
+            path_buf.push(dir);
+            path_buf.push("contrib");
+            path_buf.push(file_name);
+
+            let pla_parser = PlaParser::new(path_buf.as_path()).unwrap();
+            assert_eq!(Vec::<PlaViolation>::new(), pla_parser.validate());
+        }
+    }
+
+    #[test]
+    fn it_should_report_an_entry_starting_before_its_dependency_ends() {
+        let pla_parser = parse(r#"[89] Brewing Process
+    duration 6
+    start 2020-10-20 16
+
+[90] Brewing Transfer
+    duration 0
+    start 2020-10-20 20
+    dep 89
+"#);
+
+        let violations = pla_parser.validate();
+        assert_eq!(vec![PlaViolation::DependencyNotFinished {
+            entry_id: 90,
+            dependency_id: 89,
+            line_number: 8,
+            start: NaiveDate::from_ymd(2020, 10, 20).and_hms(20, 0, 0),
+            dependency_end: NaiveDate::from_ymd(2020, 10, 20).and_hms(22, 0, 0),
+        }], violations);
+        assert_eq!("line 8: entry 90 starts at 2020-10-20 20:00 before dependency 89 ends at 2020-10-20 22:00",
+                   format!("{}", violations[0]));
+    }
+
+    #[test]
+    fn it_should_report_dangling_ids() {
+        let pla_parser = parse(r#"[10000] Autumn's Early Arrival Blonde
+    child 89
+    child 4242
+
+[89] Brewing Process
+    duration 6
+    start 2020-10-20 16
+    dep 77
+"#);
+
+        assert_eq!(vec![
+            PlaViolation::DanglingChild { entry_id: 10000, child_id: 4242, line_number: 3 },
+            PlaViolation::DanglingDependency { entry_id: 89, dependency_id: 77, line_number: 8 },
+        ], pla_parser.validate());
+    }
+
+    #[test]
+    fn it_should_report_a_dependency_cycle() {
+        let pla_parser = parse(r#"[1] First
+    duration 1
+    dep 2
+
+[2] Second
+    duration 1
+    dep 1
+"#);
+
+        let violations = pla_parser.validate();
+        assert_eq!(vec![PlaViolation::Cycle { path: vec![1, 2, 1] }], violations);
+        assert_eq!(None, violations[0].get_line_number());
+    }
+
+    #[test]
+    fn it_should_report_an_entry_that_ends_too_late_to_schedule() {
+        let pla_parser = parse(r#"[1] First
+    start +262143-12-31 16
+    duration 1w
+"#);
+
+        assert_eq!(vec![PlaViolation::OutOfRange { entry_id: 1, line_number: 3 }], pla_parser.validate());
+    }
+
+    #[test]
+    fn it_should_report_an_entry_without_a_duration_at_its_start_block() {
+        // The parser rejects zoned starts this close to the limits, so build the entry directly.
+        let mut entry = get_entry(1, "First", &[]);
+        entry.line_number = 1;
+        entry.blocks.push(PlaSubBlock::from(PlaStartBlock {
+            parent_id: 1,
+            line_number: 2,
+            time: MAX_DATE.and_hms(16, 0, 0),
+            zone: Some(PlaTimeZone::UTC),
+        }));
+
+        assert_eq!(vec![PlaViolation::OutOfRange { entry_id: 1, line_number: 2 }], validate(&[entry]));
+    }
+}
@@ -0,0 +1,124 @@
+use chrono::{Datelike, NaiveDate};
+use chrono::naive::MAX_DATE;
+
+/// A single month spanned by a [`Calendar`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct CalendarMonth {
+    pub name: String,
+    pub year: i32,
+    pub month: u32,
+    pub num_days: u32,
+}
+
+/// Range of whole months covering a schedule, used to lay out the header rows of the grid.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Calendar {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub months: Vec<CalendarMonth>,
+}
+
+impl Calendar {
+    /// Create a calendar spanning `first` through `last`, expanded to the first day of the
+    /// starting month and the last day of the ending month.
+    pub fn new(first: NaiveDate, last: NaiveDate) -> Calendar {
+        let (first, last) = if first <= last { (first, last) } else { (last, first) };
+
+        let mut months: Vec<CalendarMonth> = vec![];
+        let mut year = first.year();
+        let mut month_num = first.month();
+        while (year, month_num) <= (last.year(), last.month()) {
+            let month_start = NaiveDate::from_ymd(year, month_num, 1);
+            months.push(CalendarMonth {
+                name: format!("{}", month_start.format("%B %Y")),
+                year,
+                month: month_num,
+                num_days: get_days_in_month(year, month_num) as u32,
+            });
+
+            month_num += 1;
+            if month_num > 12 {
+                month_num = 1;
+                year += 1;
+            }
+        }
+
+        let start_date = NaiveDate::from_ymd(first.year(), first.month(), 1);
+        let end_date = NaiveDate::from_ymd(last.year(), last.month(), get_days_in_month(last.year(), last.month()) as u32);
+
+        Calendar {
+            start_date,
+            end_date,
+            months,
+        }
+    }
+
+    /// Total number of days (and therefore day columns) in the calendar.
+    pub fn num_days(&self) -> i64 {
+        (self.end_date - self.start_date).num_days() + 1
+    }
+
+    /// Zero-based column index of `date` within the calendar.
+    pub fn day_index(&self, date: NaiveDate) -> i64 {
+        (date - self.start_date).num_days()
+    }
+
+    /// Human-readable range, e.g. "October 2021 - December 2021".
+    pub fn title(&self) -> String {
+        match (self.months.first(), self.months.last()) {
+            (Some(first), Some(last)) => format!("{} - {}", first.name, last.name),
+            _ => String::from(""),
+        }
+    }
+}
+
+pub fn get_days_in_month(year: i32, month: u32) -> i64 {
+    let month_start = NaiveDate::from_ymd(year, month, 1);
+    let (next_year, next_month) = if month == 12 { (year + 1, 1) } else { (year, month + 1) };
+
+    match NaiveDate::from_ymd_opt(next_year, next_month, 1) {
+        Some(next_month_start) => (next_month_start - month_start).num_days(),
+
+        // The last month chrono can represent, which runs to the last date it can
+        None => (MAX_DATE - month_start).num_days() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::render::calendar::{Calendar, get_days_in_month};
+
+    #[test]
+    fn it_should_get_the_number_of_days_in_a_month() {
+        assert_eq!(31, get_days_in_month(2021, 12));
+        assert_eq!(28, get_days_in_month(2021, 2));
+        assert_eq!(29, get_days_in_month(2020, 2));
+        assert_eq!(31, get_days_in_month(262143, 12));
+    }
+
+    #[test]
+    fn it_should_end_at_the_last_date_that_can_be_represented() {
+        let calendar = Calendar::new(NaiveDate::from_ymd(262143, 11, 20), NaiveDate::from_ymd(262143, 12, 31));
+        assert_eq!(NaiveDate::from_ymd(262143, 12, 31), calendar.end_date);
+        assert_eq!(61, calendar.num_days());
+    }
+
+    #[test]
+    fn it_should_expand_a_calendar_to_whole_months() {
+        let calendar = Calendar::new(NaiveDate::from_ymd(2021, 10, 12), NaiveDate::from_ymd(2021, 12, 3));
+        assert_eq!(NaiveDate::from_ymd(2021, 10, 1), calendar.start_date);
+        assert_eq!(NaiveDate::from_ymd(2021, 12, 31), calendar.end_date);
+        assert_eq!(3, calendar.months.len());
+        assert_eq!(92, calendar.num_days());
+        assert_eq!(11, calendar.day_index(NaiveDate::from_ymd(2021, 10, 12)));
+        assert_eq!("October 2021 - December 2021", calendar.title());
+    }
+
+    #[test]
+    fn it_should_span_a_year_boundary() {
+        let calendar = Calendar::new(NaiveDate::from_ymd(2020, 12, 30), NaiveDate::from_ymd(2021, 1, 2));
+        let names: Vec<String> = calendar.months.iter().map(|m| m.name.clone()).collect();
+        assert_eq!(vec!["December 2020", "January 2021"], names);
+    }
+}
@@ -0,0 +1,545 @@
+use chrono::Timelike;
+use horrorshow::helper::doctype;
+use horrorshow::{html, Raw};
+use crate::render::calendar::Calendar;
+use crate::render::rows::{get_extent, GanttBar, GanttLink, GanttRow};
+
+/// Width, in pixels, of a single day column in the grid.
+pub const DAY_WIDTH_PX: i64 = 45;
+
+/// Smallest width, in pixels, a bar is drawn with, so zero-length tasks remain visible.
+pub const MIN_BAR_WIDTH_PX: f64 = 4.0;
+
+const STYLESHEET: &str = r#"
+        body {
+            font-family: sans-serif;
+            font-size: 10pt;
+        }
+
+        table {
+            border-collapse: collapse;
+            position: relative;
+            border-spacing: 0;
+        }
+
+        .monthName {
+            text-align: center;
+            font-size: 32pt;
+            font-family: sans-serif;
+        }
+
+        td.emptyCell {
+            background-color: white !important;
+            border: none !important;
+        }
+
+        td.beerTitle {
+            font-size: 14pt;
+            font-family: sans-serif;
+            padding-right: 1rem;
+            width: 200px;
+        }
+
+        td.beerTitle.summaryTitle {
+            font-weight: bold;
+        }
+
+        .beerTitle-spacer {
+            padding-top: 1rem;
+            padding-bottom: 1rem;
+        }
+
+        td.day {
+            border-spacing: 0;
+        }
+
+        td.day, td.dayOfMonth {
+            padding: 0;
+        }
+
+        td.dayOfMonth {
+          border-bottom: 1px solid black;
+        }
+
+        td.lastDayOfMonth:not(:last-child) {
+            border-right: 3px solid black
+        }
+
+        td:not(.monthName):not(.headerRow):nth-child(2n) {
+            background-color: lightgray;
+        }
+
+        td.headerRow {
+            position: relative;
+            overflow: visible;
+        }
+
+        td.headerRow:nth-child(2n+1) {
+            background-color: lightgray;
+        }
+
+        div.full-bubble {
+          position: absolute;
+          top: calc(50% - 13px);
+          border-radius: 4px;
+          border: 1px solid darkgray;
+          background-color: lightgreen;
+          padding: .25rem;
+          z-index: 25;
+          white-space: nowrap;
+          box-sizing: border-box;
+        }
+
+        div.full-bubble.summary {
+          background-color: steelblue;
+          color: white;
+          border-radius: 0;
+        }
+
+        div.full-bubble.conflict {
+          background-color: salmon;
+          border-color: darkred;
+          opacity: .85;
+        }
+
+        div.full-bubble div.progress {
+          position: absolute;
+          top: 0;
+          bottom: 0;
+          left: 0;
+          z-index: -1;
+          background-color: rgba(0, 0, 0, .2);
+        }
+
+        div.full-bubble.milestone {
+          padding-left: 0;
+          padding-right: 0;
+          border-width: 1px 2px;
+        }
+
+        div.spacer {
+          width: 45px;
+          padding: 0;
+          margin: 0;
+          text-align: center;
+        }
+
+        div.chart {
+          position: relative;
+          display: inline-block;
+        }
+
+        svg.dependencies {
+          position: absolute;
+          top: 0;
+          left: 0;
+          pointer-events: none;
+          z-index: 30;
+        }
+
+        svg.dependencies path.link {
+          fill: none;
+          stroke: dimgray;
+          stroke-width: 1.5;
+        }
+    "#;
+
+const DEPENDENCY_OVERLAY: &str = r#"<svg class="dependencies" xmlns="http://www.w3.org/2000/svg">
+        <defs>
+            <marker id="arrowhead" viewBox="0 0 6 6" refX="6" refY="3" markerWidth="6" markerHeight="6" orient="auto">
+                <path d="M0,0 L6,3 L0,6 z" fill="dimgray"/>
+            </marker>
+        </defs>
+    </svg>"#;
+
+/// Rows grow with the length of their titles, so bar positions are only known once the page has
+/// been laid out. Arrows are drawn from there, and redrawn whenever the layout changes.
+const DEPENDENCY_SCRIPT: &str = r#"
+        function drawDependencies() {
+            var chart = document.querySelector('div.chart');
+            var svg = chart.querySelector('svg.dependencies');
+            var origin = chart.getBoundingClientRect();
+            svg.setAttribute('width', chart.scrollWidth);
+            svg.setAttribute('height', chart.scrollHeight);
+            svg.querySelectorAll('path.link').forEach(function (path) { path.remove(); });
+
+            dependencyLinks.forEach(function (link) {
+                var from = chart.querySelector('[data-entry-id="' + link[0] + '"]');
+                var to = chart.querySelector('[data-entry-id="' + link[1] + '"]');
+                if (!from || !to) {
+                    return;
+                }
+
+                var a = from.getBoundingClientRect();
+                var b = to.getBoundingClientRect();
+                var x1 = a.right - origin.left;
+                var y1 = a.top + a.height / 2 - origin.top;
+                var x2 = b.left - origin.left;
+                var y2 = b.top + b.height / 2 - origin.top;
+                var bend = x1 + 8;
+
+                var path = document.createElementNS('http://www.w3.org/2000/svg', 'path');
+                path.setAttribute('class', 'link');
+                path.setAttribute('d', 'M' + x1 + ',' + y1 + ' H' + bend + ' V' + y2 + ' H' + x2);
+                path.setAttribute('marker-end', 'url(#arrowhead)');
+                svg.appendChild(path);
+            });
+        }
+
+        window.addEventListener('load', drawDependencies);
+        window.addEventListener('resize', drawDependencies);
+    "#;
+
+/// Offset, in pixels, of the start of a bar from the left edge of its starting day cell.
+pub(crate) fn left_px(bar: &GanttBar) -> f64 {
+    let hours = bar.start.hour() as f64 + bar.start.minute() as f64 / 60.0;
+    hours / 24.0 * DAY_WIDTH_PX as f64
+}
+
+/// Width, in pixels, of a bar, proportional to its length in hours.
+pub(crate) fn width_px(bar: &GanttBar) -> f64 {
+    let hours = (bar.end - bar.start).num_minutes() as f64 / 60.0;
+    (hours / 24.0 * DAY_WIDTH_PX as f64).max(MIN_BAR_WIDTH_PX)
+}
+
+fn bar_style(bar: &GanttBar) -> String {
+    let position = format!("left: {:.2}px; width: {:.2}px", left_px(bar), width_px(bar));
+
+    // Conflicts keep their highlight whatever colour the entry asked for.
+    match &bar.color {
+        Some(color) if !bar.is_conflict => format!("{}; background-color: {}", position, color),
+        _ => position,
+    }
+}
+
+fn bar_class(bar: &GanttBar) -> String {
+    let mut classes: Vec<&str> = vec!["full-bubble"];
+    if bar.is_summary {
+        classes.push("summary");
+    } else if bar.is_milestone() {
+        classes.push("milestone");
+    }
+
+    if bar.is_conflict {
+        classes.push("conflict");
+    }
+
+    classes.join(" ")
+}
+
+fn title_class(row: &GanttRow) -> &'static str {
+    if row.is_summary { "beerTitle summaryTitle" } else { "beerTitle" }
+}
+
+fn indent_style(row: &GanttRow) -> String {
+    format!("padding-left: {}rem", row.depth as f64 * 1.5)
+}
+
+/// Links as a javascript array of `[from, to]` entry id pairs.
+fn links_script(links: &[GanttLink]) -> String {
+    let pairs: Vec<String> = links
+        .iter()
+        .map(|l| format!("[{}, {}]", l.from_entry_id, l.to_entry_id))
+        .collect();
+
+    format!("var dependencyLinks = [{}];", pairs.join(", "))
+}
+
+/// Renders rows of a chart as an HTML grid, with a column per day beneath a header of months.
+pub struct HtmlRenderer<'a> {
+    rows: &'a [GanttRow],
+    links: &'a [GanttLink],
+    tooltips: bool,
+}
+
+impl<'a> HtmlRenderer<'a> {
+    pub fn new(rows: &'a [GanttRow]) -> HtmlRenderer<'a> {
+        HtmlRenderer {
+            rows,
+            links: &[],
+            tooltips: false,
+        }
+    }
+
+    /// Draw an arrow for each link, from the end of its first entry's bar to the start of its
+    /// second's. Links to entries without a bar are skipped.
+    pub fn with_links(self, links: &'a [GanttLink]) -> HtmlRenderer<'a> {
+        HtmlRenderer {
+            links,
+            ..self
+        }
+    }
+
+    /// Show the notes of each bar's entry when hovering over it.
+    pub fn with_tooltips(self) -> HtmlRenderer<'a> {
+        HtmlRenderer {
+            tooltips: true,
+            ..self
+        }
+    }
+
+    /// Calendar covering every bar, or `None` if there aren't any.
+    pub fn calendar(&self) -> Option<Calendar> {
+        let (start, end) = get_extent(self.rows)?;
+
+        Some(Calendar::new(start.date(), end.date()))
+    }
+
+    fn tooltip(&self, bar: &GanttBar) -> Option<String> {
+        if !self.tooltips || bar.notes.is_empty() {
+            return None;
+        }
+
+        Some(bar.notes.join("\n"))
+    }
+
+    pub fn render(&self) -> String {
+        let calendar = self.calendar();
+        let title = calendar.as_ref().map_or(String::from(""), |c| c.title());
+        let months = calendar.as_ref().map_or(vec![], |c| c.months.clone());
+        let num_days = calendar.as_ref().map_or(0, |c| c.num_days());
+        let bar_indices: Vec<Vec<i64>> = self.rows
+            .iter()
+            .map(|r| {
+                r.bars
+                    .iter()
+                    .map(|b| calendar.as_ref().map_or(0, |c| c.day_index(b.start.date())))
+                    .collect()
+            })
+            .collect();
+
+        format!("{}", html! {
+            : doctype::HTML;
+            html {
+                head {
+                    title : &title;
+                    style : Raw(STYLESHEET);
+                }
+                body {
+                    div(class="chart") {
+                        table {
+                            tr {
+                                td(class="emptyCell") {}
+                                td(class="emptyCell") {}
+                                @ for month in &months {
+                                    td(class="monthName", colspan=format!("{}", month.num_days)) {
+                                        : &month.name;
+                                    }
+                                }
+                            }
+
+                            tr {
+                                td(class="emptyCell") {}
+                                td(class="emptyCell") {}
+                                @ for month in &months {
+                                    @ for j in 1..(month.num_days + 1) {
+                                        td(class = if j == month.num_days { "day lastDayOfMonth dayOfMonth" } else { "dayOfMonth" }) {
+                                            div(class="spacer") {
+                                                : j;
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+
+                            @ for (row, row_indices) in self.rows.iter().zip(bar_indices.iter()) {
+                                tr {
+                                    td(class=title_class(row), colspan="2") {
+                                        div(class="beerTitle-spacer", style=indent_style(row)) {
+                                            : &row.title;
+                                        }
+                                    }
+
+                                    @ for day in 0..num_days {
+                                        td(class="day headerRow") {
+                                            @ for (bar, bar_index) in row.bars.iter().zip(row_indices.iter()) {
+                                                @ if day == *bar_index {
+                                                    div(class=bar_class(bar), style=bar_style(bar), data-entry-id=bar.entry_id, title?=self.tooltip(bar)) {
+                                                        @ if let Some(progress) = bar.progress {
+                                                            div(class="progress", style=format!("width: {:.0}%", progress)) {}
+                                                        }
+                                                        : &bar.label;
+                                                    }
+                                                }
+                                            }
+                                        }
+                                    }
+                                }
+                            }
+                        }
+
+                        @ if !self.links.is_empty() {
+                            : Raw(DEPENDENCY_OVERLAY);
+                        }
+                    }
+
+                    @ if !self.links.is_empty() {
+                        script : Raw(links_script(self.links));
+                        script : Raw(DEPENDENCY_SCRIPT);
+                    }
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+    use crate::pla::entry::PlaEntry;
+    use crate::pla::parser::PlaParser;
+    use crate::pla::fixtures::{get_entry, get_parent, get_task};
+    use crate::pla::schedule::PlaSchedule;
+    use crate::pla::tree::PlaTree;
+    use crate::render::html::HtmlRenderer;
+    use crate::render::rows::{dependency_links, resource_rows, task_rows};
+
+    fn render(entries: &[PlaEntry]) -> String {
+        let tree = PlaTree::new(entries).unwrap();
+        let schedule = PlaSchedule::new(entries).unwrap();
+        HtmlRenderer::new(&task_rows(&tree, &schedule)).render()
+    }
+
+    #[test]
+    fn it_should_derive_the_calendar_from_the_entries() {
+        let entries = vec![
+            get_task(89, "Brewing Process", "start 2020-10-20 16", "duration 6"),
+            get_task(240, "Primary Fermentation Process", "start 2020-10-20 22", "duration 1000"),
+        ];
+
+        let tree = PlaTree::new(&entries).unwrap();
+        let schedule = PlaSchedule::new(&entries).unwrap();
+        let rows = task_rows(&tree, &schedule);
+        let calendar = HtmlRenderer::new(&rows).calendar().unwrap();
+        assert_eq!(NaiveDate::from_ymd(2020, 10, 1), calendar.start_date);
+        assert_eq!(NaiveDate::from_ymd(2020, 12, 31), calendar.end_date);
+    }
+
+    #[test]
+    fn it_should_render_a_bubble_per_scheduled_entry() {
+        let entries = vec![
+            get_task(89, "Brewing Process", "start 2020-10-20 16", "duration 6"),
+            get_task(240, "Primary Fermentation Process", "start 2020-10-20 22", "duration 224"),
+            get_entry(126, "No Operation", &[]),
+        ];
+
+        let html = render(&entries);
+        assert!(html.contains("<title>October 2020 - October 2020</title>"));
+        assert_eq!(2, html.matches("class=\"full-bubble\"").count());
+        assert!(html.contains("left: 30.00px; width: 11.25px"));
+        assert!(html.contains("left: 41.25px; width: 420.00px"));
+        assert!(!html.contains("No Operation"));
+    }
+
+    #[test]
+    fn it_should_render_a_bubble_in_the_color_of_its_entry() {
+        let entries = vec![
+            get_entry(89, "Brewing Process", &["start 2020-10-20 16", "duration 6", "color #a0c8ff"]),
+        ];
+
+        let html = render(&entries);
+        assert!(html.contains("style=\"left: 30.00px; width: 11.25px; background-color: #a0c8ff\""));
+    }
+
+    #[test]
+    fn it_should_fill_bubbles_as_far_as_their_progress() {
+        let entries = vec![
+            get_parent(10000, "Autumn's Early Arrival Blonde", vec![89, 240]),
+            get_entry(89, "Brewing Process", &["start 2020-10-20 16", "duration 6", "progress 100"]),
+            get_entry(240, "Primary Fermentation Process", &["start 2020-10-20 22", "duration 18", "progress 20"]),
+        ];
+
+        let html = render(&entries);
+        assert!(html.contains("<div class=\"progress\" style=\"width: 100%\"></div>Brewing Process"));
+        assert!(html.contains("<div class=\"progress\" style=\"width: 20%\"></div>Primary Fermentation Process"));
+        assert!(html.contains("<div class=\"progress\" style=\"width: 40%\"></div>Autumn's Early Arrival Blonde"));
+    }
+
+    #[test]
+    fn it_should_show_notes_as_tooltips_when_asked() {
+        let pla_parser: PlaParser = r#"[89] Brewing Process # first batch
+    // Mash Tun 15G G10
+    start 2020-10-20 16
+    duration 6
+"#.parse().unwrap();
+
+        let tree = pla_parser.build_tree().unwrap();
+        let schedule = pla_parser.schedule().unwrap();
+        let rows = task_rows(&tree, &schedule);
+
+        assert!(!HtmlRenderer::new(&rows).render().contains("title=\""));
+        assert!(HtmlRenderer::new(&rows).with_tooltips().render().contains("title=\"first batch\nMash Tun 15G G10\""));
+    }
+
+    #[test]
+    fn it_should_render_an_empty_grid_without_scheduled_entries() {
+        let html = render(&[]);
+        assert!(html.contains("<table>"));
+        assert!(!html.contains("class=\"full-bubble\""));
+        assert!(!html.contains("<script>"));
+    }
+
+    #[test]
+    fn it_should_render_zero_length_entries_as_milestones() {
+        let entries = vec![
+            get_task(91, "Brewing Clean", "start 2020-10-20 22", "duration 0"),
+        ];
+
+        let html = render(&entries);
+        assert!(html.contains("class=\"full-bubble milestone\""));
+        assert!(html.contains("left: 41.25px; width: 4.00px"));
+    }
+
+    #[test]
+    fn it_should_render_parents_as_summary_bars_above_indented_children() {
+        let entries = vec![
+            get_parent(10000, "Autumn's Early Arrival Blonde", vec![89, 240]),
+            get_task(89, "Brewing Process", "start 2020-10-20 16", "duration 6"),
+            get_task(240, "Primary Fermentation Process", "start 2020-10-20 22", "duration 224"),
+        ];
+
+        let html = render(&entries);
+        assert!(html.contains("class=\"full-bubble summary\" style=\"left: 30.00px; width: 431.25px\""));
+        assert!(html.contains("class=\"beerTitle summaryTitle\""));
+        assert_eq!(2, html.matches("style=\"padding-left: 1.5rem\"").count());
+
+        let parent_idx = html.find("Autumn's Early Arrival Blonde").unwrap();
+        let child_idx = html.find("Brewing Process").unwrap();
+        assert!(parent_idx < child_idx);
+    }
+
+    #[test]
+    fn it_should_render_a_row_per_resource_with_conflicts_highlighted() {
+        let entries = vec![
+            get_entry(240, "Primary Fermentation Process", &["start 2020-10-20 22", "duration 224", "res Fermentor 3"]),
+            get_entry(6, "Secondary Fermentation Process", &["start 2020-10-29 00", "duration 48", "res Fermentor 3"]),
+            get_entry(89, "Brewing Process", &["start 2020-10-20 16", "duration 6", "res Mash Tun"]),
+        ];
+
+        let schedule = PlaSchedule::new(&entries).unwrap();
+        let html = HtmlRenderer::new(&resource_rows(&entries, &schedule)).render();
+        assert!(html.contains(">Fermentor 3</div>"));
+        assert!(html.contains(">Mash Tun</div>"));
+        assert_eq!(2, html.matches("class=\"full-bubble conflict\"").count());
+        assert_eq!(1, html.matches("class=\"full-bubble\"").count());
+    }
+
+    #[test]
+    fn it_should_draw_arrows_between_dependent_bars() {
+        let entries = vec![
+            get_task(89, "Brewing Process", "start 2020-10-20 16", "duration 6"),
+            get_entry(240, "Primary Fermentation Process", &["start 2020-10-20 22", "duration 224", "dep 89"]),
+        ];
+
+        let tree = PlaTree::new(&entries).unwrap();
+        let schedule = PlaSchedule::new(&entries).unwrap();
+        let rows = task_rows(&tree, &schedule);
+        let links = dependency_links(&entries, &rows);
+        let html = HtmlRenderer::new(&rows).with_links(&links).render();
+
+        assert!(html.contains("data-entry-id=\"89\""));
+        assert!(html.contains("data-entry-id=\"240\""));
+        assert!(html.contains("<svg class=\"dependencies\""));
+        assert!(html.contains("var dependencyLinks = [[89, 240]];"));
+    }
+}
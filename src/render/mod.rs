@@ -0,0 +1,4 @@
+pub mod calendar;
+pub mod html;
+pub mod rows;
+pub mod svg;
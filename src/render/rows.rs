@@ -0,0 +1,221 @@
+use chrono::NaiveDateTime;
+use crate::pla::entry::PlaEntry;
+use crate::pla::resources::build_timelines;
+use crate::pla::schedule::PlaSchedule;
+use crate::pla::tree::PlaTree;
+
+/// A single bar drawn on a row of the chart.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GanttBar {
+    pub entry_id: u32,
+    pub label: String,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+
+    /// Whether this bar summarizes the children of its entry.
+    pub is_summary: bool,
+
+    /// Whether this bar overlaps another bar on the same row that belongs to a different entry.
+    pub is_conflict: bool,
+
+    /// Colour the entry asked to be drawn in with a `color` block, instead of the default.
+    pub color: Option<String>,
+
+    /// Percentage of the entry that is complete, if known.
+    pub progress: Option<f64>,
+
+    /// Comments written about the entry and its blocks, in the order they appear.
+    pub notes: Vec<String>,
+}
+
+impl GanttBar {
+    pub fn is_milestone(&self) -> bool {
+        self.end <= self.start
+    }
+}
+
+/// A single row of the chart, with a title to its left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GanttRow {
+    pub title: String,
+
+    /// How far the title is indented, e.g. how deeply an entry is nested beneath its parents.
+    pub depth: usize,
+    pub is_summary: bool,
+    pub bars: Vec<GanttBar>,
+}
+
+/// A dependency between two entries, drawn from the end of the first to the start of the second.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GanttLink {
+    pub from_entry_id: u32,
+    pub to_entry_id: u32,
+}
+
+/// Earliest start and latest end of any bar on any of the given rows.
+pub fn get_extent(rows: &[GanttRow]) -> Option<(NaiveDateTime, NaiveDateTime)> {
+    let bars = rows.iter().flat_map(|r| r.bars.iter());
+    let start = bars.clone().map(|b| b.start).min()?;
+    let end = bars.map(|b| b.end).max()?;
+
+    Some((start, end))
+}
+
+/// One row per scheduled entry, in tree order, with parents drawn as summary bars spanning their
+/// children. Entries that can't be scheduled are left out.
+pub fn task_rows(tree: &PlaTree, schedule: &PlaSchedule) -> Vec<GanttRow> {
+    tree.flatten()
+        .into_iter()
+        .filter_map(|(depth, node)| {
+            let time = schedule.get(node.entry.id)?;
+            let is_summary = node.has_children();
+
+            Some(GanttRow {
+                title: String::from(&node.entry.description),
+                depth,
+                is_summary,
+                bars: vec![GanttBar {
+                    entry_id: node.entry.id,
+                    label: String::from(&node.entry.description),
+                    start: time.start,
+                    end: time.end,
+                    is_summary,
+                    is_conflict: false,
+                    color: node.entry.get_color(),
+                    progress: node.get_progress(),
+                    notes: node.entry.notes.iter().map(|n| String::from(&n.text)).collect(),
+                }],
+            })
+        })
+        .collect()
+}
+
+/// One row per resource, in the order resources first appear, with a bar for every scheduled
+/// entry that books it. Bars of entries that double-book the resource are flagged as conflicts.
+pub fn resource_rows(entries: &[PlaEntry], schedule: &PlaSchedule) -> Vec<GanttRow> {
+    build_timelines(entries, schedule)
+        .into_iter()
+        .map(|timeline| {
+            let conflicts = timeline.find_conflicts();
+            let bars: Vec<GanttBar> = timeline.bookings
+                .iter()
+                .map(|booking| {
+                    let entry = entries.iter().find(|e| e.id == booking.entry_id);
+                    let description = entry.map_or(String::from(""), |e| String::from(&e.description));
+
+                    GanttBar {
+                        entry_id: booking.entry_id,
+                        label: description,
+                        start: booking.start,
+                        end: booking.end,
+                        is_summary: false,
+                        is_conflict: conflicts
+                            .iter()
+                            .any(|c| c.first.entry_id == booking.entry_id || c.second.entry_id == booking.entry_id),
+                        color: entry.and_then(|e| e.get_color()),
+                        progress: entry.and_then(|e| e.get_progress()).map(f64::from),
+                        notes: entry.map_or(vec![], |e| e.notes.iter().map(|n| String::from(&n.text)).collect()),
+                    }
+                })
+                .collect();
+
+            GanttRow {
+                title: timeline.resource_name,
+                depth: 0,
+                is_summary: false,
+                bars,
+            }
+        })
+        .collect()
+}
+
+/// A link for every `dep` block whose entries both have a bar on one of the given rows.
+pub fn dependency_links(entries: &[PlaEntry], rows: &[GanttRow]) -> Vec<GanttLink> {
+    let has_bar = |id: u32| rows.iter().any(|r| r.bars.iter().any(|b| b.entry_id == id));
+
+    entries
+        .iter()
+        .flat_map(|e| {
+            e.dependencies()
+                .into_iter()
+                .map(move |d| GanttLink { from_entry_id: d.dependency_id, to_entry_id: e.id })
+        })
+        .filter(|l| has_bar(l.from_entry_id) && has_bar(l.to_entry_id))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pla::parser::PlaParser;
+    use crate::render::rows::{dependency_links, get_extent, resource_rows, task_rows, GanttLink};
+
+    fn parse(text: &str) -> PlaParser {
+        PlaParser::parse(text.split('\n').map(String::from).collect()).unwrap()
+    }
+
+    const PLA_TEXT: &str = r#"[10000] Autumn's Early Arrival Blonde
+    child 89
+    child 90
+
+# Mashed in the 15 gallon tun
+[89] Brewing Process
+    color tomato # to stand out
+    duration 6
+    progress 50
+    start 2020-10-20 16
+    res Mash Tun 15G G10
+
+[90] Brewing Transfer
+    duration 0
+    start 2020-10-20 18
+    res Pumper
+    res Mash Tun 15G G10
+    dep 89
+    dep 126
+
+[126] No Operation
+"#;
+
+    #[test]
+    fn it_should_build_a_row_per_scheduled_task() {
+        let pla_parser = parse(PLA_TEXT);
+        let tree = pla_parser.build_tree().unwrap();
+        let schedule = pla_parser.schedule().unwrap();
+        let rows = task_rows(&tree, &schedule);
+
+        let titles: Vec<(usize, &str)> = rows.iter().map(|r| (r.depth, r.title.as_str())).collect();
+        assert_eq!(vec![(0, "Autumn's Early Arrival Blonde"), (1, "Brewing Process"), (1, "Brewing Transfer")], titles);
+        assert!(rows[0].bars[0].is_summary);
+        assert!(rows[2].bars[0].is_milestone());
+        assert_eq!(Some(String::from("tomato")), rows[1].bars[0].color);
+        assert_eq!(Some(50.0), rows[1].bars[0].progress);
+        assert_eq!(Some(50.0), rows[0].bars[0].progress);
+        assert_eq!(vec!["Mashed in the 15 gallon tun", "to stand out"], rows[1].bars[0].notes);
+        assert_eq!(schedule.get_extent().map(|t| (t.start, t.end)), get_extent(&rows));
+    }
+
+    #[test]
+    fn it_should_build_a_row_per_resource_and_flag_conflicts() {
+        let pla_parser = parse(PLA_TEXT);
+        let schedule = pla_parser.schedule().unwrap();
+        let rows = resource_rows(&pla_parser.entries, &schedule);
+
+        let titles: Vec<&str> = rows.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(vec!["Mash Tun 15G G10", "Pumper"], titles);
+
+        let mash_tun_ids: Vec<(u32, bool)> = rows[0].bars.iter().map(|b| (b.entry_id, b.is_conflict)).collect();
+        assert_eq!(vec![(89, true), (90, true)], mash_tun_ids);
+        assert_eq!(Some(String::from("tomato")), rows[0].bars[0].color);
+        assert!(!rows[1].bars[0].is_conflict);
+    }
+
+    #[test]
+    fn it_should_link_dependencies_between_drawn_entries() {
+        let pla_parser = parse(PLA_TEXT);
+        let tree = pla_parser.build_tree().unwrap();
+        let schedule = pla_parser.schedule().unwrap();
+        let rows = task_rows(&tree, &schedule);
+
+        assert_eq!(vec![GanttLink { from_entry_id: 89, to_entry_id: 90 }], dependency_links(&pla_parser.entries, &rows));
+    }
+}
@@ -0,0 +1,394 @@
+use std::collections::HashMap;
+use horrorshow::{html, Raw};
+use crate::render::calendar::Calendar;
+use crate::render::html::{left_px, width_px, DAY_WIDTH_PX};
+use crate::render::rows::{get_extent, GanttBar, GanttLink, GanttRow};
+
+/// Width, in pixels, of the column of row titles to the left of the grid.
+pub const TITLE_WIDTH_PX: f64 = 240.0;
+
+/// Height, in pixels, of a single row of the chart.
+pub const ROW_HEIGHT_PX: f64 = 30.0;
+
+const MONTH_HEADER_HEIGHT_PX: f64 = 32.0;
+const DAY_HEADER_HEIGHT_PX: f64 = 20.0;
+const HEADER_HEIGHT_PX: f64 = MONTH_HEADER_HEIGHT_PX + DAY_HEADER_HEIGHT_PX;
+
+/// Vertical gap, in pixels, between a bar and the edges of its row.
+const BAR_MARGIN_PX: f64 = 5.0;
+
+/// How far, in pixels, each level of nesting indents a row title.
+const INDENT_PX: f64 = 16.0;
+
+/// Horizontal distance, in pixels, an arrow travels past the end of its bar before turning.
+const LINK_BEND_PX: f64 = 8.0;
+
+const STYLESHEET: &str = r#"
+        text {
+            font-family: sans-serif;
+            font-size: 10pt;
+        }
+
+        text.monthName {
+            font-size: 16pt;
+            text-anchor: middle;
+        }
+
+        text.dayOfMonth {
+            font-size: 8pt;
+            text-anchor: middle;
+        }
+
+        text.beerTitle {
+            font-size: 11pt;
+        }
+
+        text.beerTitle.summaryTitle {
+            font-weight: bold;
+        }
+
+        rect.stripe {
+            fill: lightgray;
+        }
+
+        line.dayOfMonth {
+            stroke: black;
+        }
+
+        line.lastDayOfMonth {
+            stroke: black;
+            stroke-width: 3;
+        }
+
+        rect.full-bubble {
+            fill: lightgreen;
+            stroke: darkgray;
+        }
+
+        rect.full-bubble.summary {
+            fill: steelblue;
+        }
+
+        rect.full-bubble.conflict {
+            fill: salmon;
+            stroke: darkred;
+            fill-opacity: .85;
+        }
+
+        rect.progress {
+            fill: black;
+            fill-opacity: .2;
+        }
+
+        text.barLabel.summary {
+            fill: white;
+        }
+
+        path.link {
+            fill: none;
+            stroke: dimgray;
+            stroke-width: 1.5;
+        }
+    "#;
+
+/// Position and size of a bar within the chart, in pixels.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct BarBox {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+}
+
+impl BarBox {
+    fn middle(&self) -> f64 {
+        self.y + self.height / 2.0
+    }
+}
+
+fn px(value: f64) -> String {
+    format!("{:.2}", value)
+}
+
+fn row_top(row_index: usize) -> f64 {
+    HEADER_HEIGHT_PX + row_index as f64 * ROW_HEIGHT_PX
+}
+
+fn bar_class(bar: &GanttBar) -> String {
+    let mut classes: Vec<&str> = vec!["full-bubble"];
+    if bar.is_summary {
+        classes.push("summary");
+    }
+
+    if bar.is_conflict {
+        classes.push("conflict");
+    }
+
+    classes.join(" ")
+}
+
+/// Fill for the bar of an entry that asked for a colour, unless the bar is highlighted as a conflict.
+fn bar_style(bar: &GanttBar) -> Option<String> {
+    match &bar.color {
+        Some(color) if !bar.is_conflict => Some(format!("fill: {}", color)),
+        _ => None,
+    }
+}
+
+fn label_class(bar: &GanttBar) -> &'static str {
+    if bar.is_summary { "barLabel summary" } else { "barLabel" }
+}
+
+fn title_class(row: &GanttRow) -> &'static str {
+    if row.is_summary { "beerTitle summaryTitle" } else { "beerTitle" }
+}
+
+/// Elbow connector from the end of `from` to the start of `to`.
+fn link_path(from: &BarBox, to: &BarBox) -> String {
+    let x1 = from.x + from.width;
+    let bend = x1 + LINK_BEND_PX;
+
+    format!("M{},{} H{} V{} H{}", px(x1), px(from.middle()), px(bend), px(to.middle()), px(to.x))
+}
+
+/// Renders rows of a chart as a self-contained SVG image, with a column per day beneath a header
+/// of months. Unlike [`HtmlRenderer`](crate::render::html::HtmlRenderer), every row has the same
+/// height, so positions are fixed when the image is written and it can be embedded or printed
+/// without a browser laying it out.
+pub struct SvgRenderer<'a> {
+    rows: &'a [GanttRow],
+    links: &'a [GanttLink],
+}
+
+impl<'a> SvgRenderer<'a> {
+    pub fn new(rows: &'a [GanttRow]) -> SvgRenderer<'a> {
+        SvgRenderer {
+            rows,
+            links: &[],
+        }
+    }
+
+    /// Draw an arrow for each link, from the end of its first entry's bar to the start of its
+    /// second's. Links to entries without a bar are skipped.
+    pub fn with_links(self, links: &'a [GanttLink]) -> SvgRenderer<'a> {
+        SvgRenderer {
+            links,
+            ..self
+        }
+    }
+
+    /// Calendar covering every bar, or `None` if there aren't any.
+    pub fn calendar(&self) -> Option<Calendar> {
+        let (start, end) = get_extent(self.rows)?;
+
+        Some(Calendar::new(start.date(), end.date()))
+    }
+
+    fn bar_boxes(&self, calendar: &Option<Calendar>) -> Vec<Vec<BarBox>> {
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(idx, row)| {
+                row.bars
+                    .iter()
+                    .map(|bar| {
+                        let day_index = calendar.as_ref().map_or(0, |c| c.day_index(bar.start.date()));
+                        BarBox {
+                            x: TITLE_WIDTH_PX + (day_index * DAY_WIDTH_PX) as f64 + left_px(bar),
+                            y: row_top(idx) + BAR_MARGIN_PX,
+                            width: width_px(bar),
+                            height: ROW_HEIGHT_PX - 2.0 * BAR_MARGIN_PX,
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    pub fn render(&self) -> String {
+        let calendar = self.calendar();
+        let title = calendar.as_ref().map_or(String::from(""), |c| c.title());
+        let months = calendar.as_ref().map_or(vec![], |c| c.months.clone());
+        let num_days = calendar.as_ref().map_or(0, |c| c.num_days());
+        let width = TITLE_WIDTH_PX + (num_days * DAY_WIDTH_PX) as f64;
+        let height = row_top(self.rows.len());
+        let day_width = DAY_WIDTH_PX as f64;
+
+        let bar_boxes = self.bar_boxes(&calendar);
+
+        // Where an entry is drawn more than once, arrows start and end at its first bar.
+        let mut entry_boxes: HashMap<u32, BarBox> = HashMap::new();
+        for (row, boxes) in self.rows.iter().zip(bar_boxes.iter()) {
+            for (bar, bar_box) in row.bars.iter().zip(boxes.iter()) {
+                entry_boxes.entry(bar.entry_id).or_insert(*bar_box);
+            }
+        }
+
+        let link_paths: Vec<String> = self.links
+            .iter()
+            .filter_map(|l| {
+                let from = entry_boxes.get(&l.from_entry_id)?;
+                let to = entry_boxes.get(&l.to_entry_id)?;
+                Some(link_path(from, to))
+            })
+            .collect();
+
+        let mut month_offsets: Vec<f64> = vec![];
+        let mut offset = TITLE_WIDTH_PX;
+        for month in &months {
+            month_offsets.push(offset);
+            offset += month.num_days as f64 * day_width;
+        }
+
+        format!("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n{}", html! {
+            svg(xmlns="http://www.w3.org/2000/svg", width=px(width), height=px(height),
+                viewBox=format!("0 0 {} {}", px(width), px(height))) {
+                title : &title;
+                style : Raw(STYLESHEET);
+                defs {
+                    marker(id="arrowhead", viewBox="0 0 6 6", refX="6", refY="3", markerWidth="6",
+                           markerHeight="6", orient="auto") {
+                        path(d="M0,0 L6,3 L0,6 z", fill="dimgray") {}
+                    }
+                }
+
+                rect(width=px(width), height=px(height), fill="white") {}
+
+                @ for day in (1..num_days).step_by(2) {
+                    rect(class="stripe", x=px(TITLE_WIDTH_PX + day as f64 * day_width), y=px(MONTH_HEADER_HEIGHT_PX),
+                         width=px(day_width), height=px(height - MONTH_HEADER_HEIGHT_PX)) {}
+                }
+
+                @ for (month, x) in months.iter().zip(month_offsets.iter()) {
+                    text(class="monthName", x=px(x + month.num_days as f64 * day_width / 2.0),
+                         y=px(MONTH_HEADER_HEIGHT_PX / 2.0), dy="0.35em") {
+                        : &month.name;
+                    }
+
+                    @ for j in 1..(month.num_days + 1) {
+                        text(class="dayOfMonth", x=px(x + (j as f64 - 0.5) * day_width),
+                             y=px(MONTH_HEADER_HEIGHT_PX + DAY_HEADER_HEIGHT_PX / 2.0), dy="0.35em") {
+                            : j;
+                        }
+                    }
+
+                    line(class="lastDayOfMonth", x1=px(x + month.num_days as f64 * day_width),
+                         y1=px(MONTH_HEADER_HEIGHT_PX), x2=px(x + month.num_days as f64 * day_width), y2=px(height)) {}
+                }
+
+                line(class="dayOfMonth", x1=px(TITLE_WIDTH_PX), y1=px(HEADER_HEIGHT_PX), x2=px(width), y2=px(HEADER_HEIGHT_PX)) {}
+
+                @ for (idx, row) in self.rows.iter().enumerate() {
+                    text(class=title_class(row), x=px(BAR_MARGIN_PX + row.depth as f64 * INDENT_PX),
+                         y=px(row_top(idx) + ROW_HEIGHT_PX / 2.0), dy="0.35em") {
+                        : &row.title;
+                    }
+                }
+
+                @ for (row, boxes) in self.rows.iter().zip(bar_boxes.iter()) {
+                    @ for (bar, bar_box) in row.bars.iter().zip(boxes.iter()) {
+                        g(data-entry-id=bar.entry_id) {
+                            rect(class=bar_class(bar), x=px(bar_box.x), y=px(bar_box.y), width=px(bar_box.width),
+                                 height=px(bar_box.height), rx=if bar.is_summary { "0" } else { "4" }, style?=bar_style(bar)) {}
+                            @ if let Some(progress) = bar.progress {
+                                rect(class="progress", x=px(bar_box.x), y=px(bar_box.y), width=px(bar_box.width * progress / 100.0),
+                                     height=px(bar_box.height)) {}
+                            }
+                            text(class=label_class(bar), x=px(bar_box.x + BAR_MARGIN_PX), y=px(bar_box.middle()), dy="0.35em") {
+                                : &bar.label;
+                            }
+                        }
+                    }
+                }
+
+                @ for d in &link_paths {
+                    path(class="link", d=d, marker-end="url(#arrowhead)") {}
+                }
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::pla::entry::PlaEntry;
+    use crate::pla::schedule::PlaSchedule;
+    use crate::pla::fixtures::{get_entry, get_task};
+    use crate::pla::tree::PlaTree;
+    use crate::render::rows::{dependency_links, task_rows};
+    use crate::render::svg::SvgRenderer;
+
+    fn render(entries: &[PlaEntry]) -> String {
+        let tree = PlaTree::new(entries).unwrap();
+        let schedule = PlaSchedule::new(entries).unwrap();
+        let rows = task_rows(&tree, &schedule);
+        let links = dependency_links(entries, &rows);
+        SvgRenderer::new(&rows).with_links(&links).render()
+    }
+
+    #[test]
+    fn it_should_render_a_bar_per_scheduled_entry() {
+        let entries = vec![
+            get_task(89, "Brewing Process", "start 2020-10-20 16", "duration 6"),
+            get_task(240, "Primary Fermentation Process", "start 2020-10-20 22", "duration 224"),
+        ];
+
+        let svg = render(&entries);
+        assert!(svg.starts_with("<?xml"));
+        assert!(svg.contains("<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"1635.00\" height=\"112.00\""));
+        assert!(svg.contains("<title>October 2020 - October 2020</title>"));
+        assert!(svg.contains("x=\"1125.00\" y=\"57.00\" width=\"11.25\" height=\"20.00\""));
+        assert!(svg.contains("x=\"1136.25\" y=\"87.00\" width=\"420.00\" height=\"20.00\""));
+        assert_eq!(2, svg.matches("class=\"full-bubble\"").count());
+    }
+
+    #[test]
+    fn it_should_draw_arrows_between_dependent_bars() {
+        let entries = vec![
+            get_task(89, "Brewing Process", "start 2020-10-20 16", "duration 6"),
+            get_entry(240, "Primary Fermentation Process", &["start 2020-10-20 22", "duration 224", "dep 89"]),
+        ];
+
+        let svg = render(&entries);
+        assert!(svg.contains("d=\"M1136.25,67.00 H1144.25 V97.00 H1136.25\""));
+        assert!(svg.contains("marker-end=\"url(#arrowhead)\""));
+    }
+
+    #[test]
+    fn it_should_fill_a_bar_in_the_color_of_its_entry() {
+        let entries = vec![
+            get_entry(89, "Brewing Process", &["start 2020-10-20 16", "duration 6", "color gold"]),
+            get_task(240, "Primary Fermentation Process", "start 2020-10-20 22", "duration 224"),
+        ];
+
+        let svg = render(&entries);
+        assert_eq!(1, svg.matches("style=\"fill: gold\"").count());
+    }
+
+    #[test]
+    fn it_should_fill_bars_as_far_as_their_progress() {
+        let entries = vec![
+            get_entry(240, "Primary Fermentation Process", &["start 2020-10-20 22", "duration 224", "progress 25"]),
+        ];
+
+        let svg = render(&entries);
+        assert!(svg.contains("class=\"progress\" x=\"1136.25\" y=\"57.00\" width=\"105.00\" height=\"20.00\""));
+    }
+
+    #[test]
+    fn it_should_escape_titles() {
+        let entries = vec![
+            get_task(89, "Mash & Sparge <Batch 1>", "start 2020-10-20 16", "duration 6"),
+        ];
+
+        let svg = render(&entries);
+        assert!(svg.contains("Mash &amp; Sparge &lt;Batch 1&gt;"));
+    }
+
+    #[test]
+    fn it_should_render_an_empty_chart_without_scheduled_entries() {
+        let svg = render(&[]);
+        assert!(svg.contains("width=\"240.00\" height=\"52.00\""));
+        assert!(!svg.contains("class=\"full-bubble\""));
+    }
+}
@@ -0,0 +1,44 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+/// Run pla2html on `text`, written to a file named `name`, and render it as HTML with `args`.
+fn render(name: &str, text: &str, args: &[&str]) -> Output {
+    let dir = PathBuf::from(env!("CARGO_TARGET_TMPDIR"));
+    let input = dir.join(name);
+    fs::write(&input, text).unwrap();
+
+    Command::new(env!("CARGO_BIN_EXE_pla2html"))
+        .arg("-i").arg(&input)
+        .arg("-o").arg(dir.join(format!("{}.html", name)))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn it_should_report_a_dependency_cycle_without_panicking() {
+    let output = render("cycle.pla", "[1] a\n    dep 2\n\n[2] b\n    dep 1\n", &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(Some(1), output.status.code());
+    assert!(stderr.contains("error: Cycle detected in entry dependencies: 1 -> 2 -> 1"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn it_should_report_a_missing_child_without_panicking() {
+    let output = render("missing_child.pla", "[1] a\n    child 2\n    start 2021-01-01\n    duration 1\n", &[]);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert_eq!(Some(1), output.status.code());
+    assert!(stderr.contains("error: Unable to schedule entries: Entry 1 lists child 2, which does not exist"), "{}", stderr);
+    assert!(!stderr.contains("panicked"), "{}", stderr);
+}
+
+#[test]
+fn it_should_accept_a_time_zone_west_of_utc() {
+    let output = render("west.pla", "[1] a\n    start 2021-01-01 12:00 +01:00\n    duration 1\n", &["--timezone", "-05:00"]);
+
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+}